@@ -0,0 +1,121 @@
+use ggez::{
+    glam::Vec2,
+    graphics::{self, Color},
+};
+use serde::{Deserialize, Serialize};
+
+use super::{Capabilities, DrawInstruction, Drawable, Entity, EntityKind, RigidBody};
+use crate::system::state::GameState;
+use crate::theory::geometry::Transform;
+use crate::theory::physics::{
+    BodyKind, ColliderShape, Motion, Physics, PhysicsController, RigidBodyProperty,
+};
+use crate::theory::units::to_pixels;
+
+/// Mass per square meter of the asteroid's disc, in kilograms
+const AREAL_DENSITY: f32 = 400.0;
+
+/// Craters drawn on the surface, as (angle, distance from the center, radius) relative to
+/// the asteroid's radius, so that the tumbling can be seen
+const CRATERS: [(f32, f32, f32); 3] = [(0.4, 0.5, 0.22), (2.5, 0.35, 0.15), (4.3, 0.6, 0.12)];
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Asteroid {
+    pub physics: Option<Physics>,
+    pub transform: Transform,
+    pub motion: Motion,
+    pub radius: f32,
+}
+
+impl Asteroid {
+    pub fn new(location: (f32, f32), radius: f32, motion: Motion) -> Self {
+        Self {
+            physics: None,
+            transform: Transform::new(location, 0.0),
+            motion,
+            radius,
+        }
+    }
+}
+
+impl EntityKind for Asteroid {
+    const NAME: &'static str = "asteroid";
+    const CAPABILITIES: Capabilities = Capabilities::DRAWABLE.union(Capabilities::RIGID_BODY);
+}
+
+impl Entity for Asteroid {
+    fn as_drawable(&self) -> Option<&dyn Drawable> {
+        Some(self)
+    }
+
+    fn as_rigidbody(&mut self) -> Option<&mut dyn RigidBody> {
+        Some(self)
+    }
+}
+
+impl Drawable for Asteroid {
+    fn draw(
+        &self,
+        canvas: &mut graphics::Canvas,
+        state: &GameState,
+    ) -> ggez::GameResult<DrawInstruction> {
+        let radius = to_pixels(self.radius);
+
+        canvas.draw(
+            &state.circle,
+            graphics::DrawParam::new()
+                .dest(Vec2::new(radius, radius))
+                .scale(Vec2::new(radius, radius))
+                .color(Color::from_rgb(120, 105, 95)),
+        );
+
+        CRATERS.iter().for_each(|(angle, distance, size)| {
+            canvas.draw(
+                &state.circle,
+                graphics::DrawParam::new()
+                    .dest(Vec2::new(
+                        radius + angle.cos() * distance * radius,
+                        radius + angle.sin() * distance * radius,
+                    ))
+                    .scale(Vec2::new(size * radius, size * radius))
+                    .color(Color::from_rgb(85, 75, 70)),
+            );
+        });
+
+        Ok(DrawInstruction {
+            position: self.transform.location.into(),
+            angle: self.transform.angle,
+            size: Vec2::new(radius * 2.0, radius * 2.0),
+            ..Default::default()
+        })
+    }
+}
+
+impl RigidBody for Asteroid {
+    fn get_property(&self) -> RigidBodyProperty {
+        RigidBodyProperty {
+            kind: BodyKind::Dynamic,
+            mass: AREAL_DENSITY * std::f32::consts::PI * self.radius.powi(2),
+            shape: ColliderShape::Ball {
+                radius: self.radius,
+            },
+            initial_transform: self.transform.clone(),
+            initial_motion: self.motion.clone(),
+        }
+    }
+
+    fn register_physics(&mut self, physics: Physics) {
+        self.physics = Some(physics);
+    }
+
+    fn get_mut_physics(&mut self) -> &mut Physics {
+        self.physics.as_mut().unwrap()
+    }
+
+    fn update_physics(&mut self, _controller: &mut PhysicsController) {}
+
+    fn report_transform(&mut self, transform: Transform, motion: Motion) {
+        self.transform = transform;
+        self.motion = motion;
+    }
+}
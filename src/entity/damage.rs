@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DamageSpec {
+    /// Impacts below this impulse leave no damage, in newton-seconds
+    pub threshold: f32,
+    /// Impulse above the threshold that destroys a part entirely, in newton-seconds
+    pub tolerance: f32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PartHealth {
+    pub name: String,
+    pub at: (f32, f32),
+    pub health: f32,
+}
+
+impl Default for DamageSpec {
+    fn default() -> Self {
+        Self {
+            threshold: 500.0,
+            tolerance: 5000.0,
+        }
+    }
+}
+
+impl DamageSpec {
+    pub fn damage(&self, impulse: f32) -> f32 {
+        ((impulse - self.threshold) / self.tolerance).max(0.0)
+    }
+}
+
+impl PartHealth {
+    pub fn new(name: &str, at: (f32, f32)) -> Self {
+        Self {
+            name: name.to_string(),
+            at,
+            health: 1.0,
+        }
+    }
+
+    pub fn damage(&self) -> f32 {
+        1.0 - self.health
+    }
+}
+
+/// Damages the part nearest to `point` and returns it, if the impulse was strong enough.
+pub fn apply_impact<'a>(
+    parts: &'a mut [PartHealth],
+    spec: &DamageSpec,
+    point: (f32, f32),
+    impulse: f32,
+) -> Option<&'a PartHealth> {
+    let damage = spec.damage(impulse);
+    if damage <= 0.0 {
+        return None;
+    }
+
+    let distance = |part: &PartHealth| (part.at.0 - point.0).hypot(part.at.1 - point.1);
+    let part = parts
+        .iter_mut()
+        .min_by(|a, b| distance(a).total_cmp(&distance(b)))?;
+
+    part.health = (part.health - damage).max(0.0);
+
+    Some(part)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn impact_should_damage_the_nearest_part() {
+        let mut parts = vec![
+            PartHealth::new("hull", (0.0, 0.0)),
+            PartHealth::new("left", (-1.0, 0.0)),
+        ];
+        let spec = DamageSpec {
+            threshold: 100.0,
+            tolerance: 1000.0,
+        };
+
+        assert!(apply_impact(&mut parts, &spec, (-0.9, 0.2), 50.0).is_none());
+
+        let damaged = apply_impact(&mut parts, &spec, (-0.9, 0.2), 600.0).unwrap();
+        assert_eq!(damaged.name, "left");
+        assert!((damaged.health - 0.5).abs() < 1e-6);
+
+        apply_impact(&mut parts, &spec, (-0.9, 0.2), 5000.0);
+        assert_eq!(parts[1].health, 0.0);
+        assert_eq!(parts[0].health, 1.0);
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use super::damage::DamageSpec;
+use super::docking::DockingPort;
+use super::payload::PayloadDesign;
+use super::radio::RadioSpec;
+use super::thruster::ThrusterSpec;
+use super::wheel::WheelSpec;
+use crate::theory::physics::ColliderShape;
+
+/// Name of the part that stands for the body itself
+pub const HULL: &str = "hull";
+
+#[derive(thiserror::Error, Debug)]
+pub enum DesignError {
+    #[error("Could not parse the design: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+
+    #[error("Invalid design, '{part}': {reason}")]
+    Invalid { part: String, reason: String },
+}
+
+/// A satellite loaded from a RON file under `assets/designs`. Lengths are in meters,
+/// the mass in kilograms and thrust in newtons.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SatelliteDesign {
+    pub name: String,
+    pub mass: f32,
+    pub size: (f32, f32),
+    pub sprite: String,
+    #[serde(default = "default_sprite_scale")]
+    pub sprite_scale: f32,
+    /// Traced from the sprite's alpha channel when omitted
+    #[serde(default)]
+    pub shape: Option<ColliderShape>,
+    pub boosters: Vec<BoosterDesign>,
+    #[serde(default)]
+    pub wheel: Option<WheelSpec>,
+    #[serde(default)]
+    pub radio: Option<RadioSpec>,
+    #[serde(default)]
+    pub damage: DamageSpec,
+    #[serde(default)]
+    pub payloads: Vec<PayloadDesign>,
+    #[serde(default)]
+    pub ports: Vec<DockingPort>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BoosterDesign {
+    pub name: String,
+    pub at: (f32, f32),
+    pub direction: (f32, f32),
+    pub thruster: ThrusterSpec,
+}
+
+fn default_sprite_scale() -> f32 {
+    1.0
+}
+
+impl SatelliteDesign {
+    pub fn parse(source: &str) -> Result<Self, DesignError> {
+        let design: Self = ron::from_str(source)?;
+
+        let invalid = |part: &str, reason: String| DesignError::Invalid {
+            part: part.to_string(),
+            reason,
+        };
+
+        if design.mass <= 0.0 {
+            return Err(invalid("mass", "mass should be positive".to_string()));
+        }
+
+        if design.size.0 <= 0.0 || design.size.1 <= 0.0 {
+            return Err(invalid("size", "size should be positive".to_string()));
+        }
+
+        if design.sprite_scale <= 0.0 {
+            return Err(invalid(
+                "sprite_scale",
+                "sprite_scale should be positive".to_string(),
+            ));
+        }
+
+        if let Some(wheel) = &design.wheel {
+            if wheel.inertia <= 0.0 {
+                return Err(invalid("wheel", "inertia should be positive".to_string()));
+            }
+
+            if wheel.max_momentum <= 0.0 {
+                return Err(invalid(
+                    "wheel",
+                    "max_momentum should be positive".to_string(),
+                ));
+            }
+        }
+
+        let mut names = HashSet::new();
+        for booster in &design.boosters {
+            if booster.name == HULL {
+                return Err(invalid(
+                    "boosters",
+                    format!("'{HULL}' is reserved for the body"),
+                ));
+            }
+
+            if !names.insert(booster.name.as_str()) {
+                return Err(invalid(
+                    "boosters",
+                    format!("Duplicated booster ({})", booster.name),
+                ));
+            }
+
+            if booster.direction.0.hypot(booster.direction.1) == 0.0 {
+                return Err(invalid(
+                    "boosters",
+                    format!("Booster ({}) has no direction", booster.name),
+                ));
+            }
+
+            if booster.thruster.max_thrust <= 0.0 {
+                return Err(invalid(
+                    "boosters",
+                    format!("Booster ({}) should have positive max_thrust", booster.name),
+                ));
+            }
+        }
+
+        let mut names = HashSet::new();
+        for payload in &design.payloads {
+            if !names.insert(payload.name.as_str()) {
+                return Err(invalid(
+                    "payloads",
+                    format!("Duplicated payload ({})", payload.name),
+                ));
+            }
+
+            if payload.direction.0.hypot(payload.direction.1) == 0.0 {
+                return Err(invalid(
+                    "payloads",
+                    format!("Payload ({}) has no direction", payload.name),
+                ));
+            }
+
+            if payload.impulse < 0.0 {
+                return Err(invalid(
+                    "payloads",
+                    format!("Payload ({}) should be pushed away", payload.name),
+                ));
+            }
+        }
+
+        let mut names = HashSet::new();
+        for port in &design.ports {
+            if !names.insert(port.name.as_str()) {
+                return Err(invalid(
+                    "ports",
+                    format!("Duplicated docking port ({})", port.name),
+                ));
+            }
+
+            if port.direction.0.hypot(port.direction.1) == 0.0 {
+                return Err(invalid(
+                    "ports",
+                    format!("Docking port ({}) has no direction", port.name),
+                ));
+            }
+        }
+
+        Ok(design)
+    }
+
+    pub fn booster(&self, name: &str) -> Option<&BoosterDesign> {
+        self.boosters.iter().find(|booster| booster.name == name)
+    }
+
+    pub fn payload(&self, name: &str) -> Option<&PayloadDesign> {
+        self.payloads.iter().find(|payload| payload.name == name)
+    }
+
+    pub fn port(&self, name: &str) -> Option<&DockingPort> {
+        self.ports.iter().find(|port| port.name == name)
+    }
+}
+
+#[cfg(test)]
+impl SatelliteDesign {
+    /// A ball-shaped probe for tests, with `parts` (at least its boosters) spliced in
+    pub fn sample(parts: &str) -> Self {
+        Self::parse(&format!(
+            r#"(
+                name: "probe",
+                mass: 10.0,
+                size: (1.0, 1.0),
+                sprite: "imgs/probe.png",
+                shape: Some(Ball(radius: 0.5)),
+                {parts}
+            )"#
+        ))
+        .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn design_should_be_parsed_with_defaults() {
+        let design = SatelliteDesign::parse(
+            r#"(
+                name: "probe",
+                mass: 10.0,
+                size: (1.0, 1.0),
+                sprite: "imgs/probe.png",
+                shape: Some(Ball(radius: 0.5)),
+                boosters: [
+                    (name: "main", at: (0.0, 0.5), direction: (0.0, -1.0), thruster: (max_thrust: 20.0)),
+                ],
+            )"#,
+        )
+        .unwrap();
+
+        assert_eq!(design.sprite_scale, 1.0);
+        assert_eq!(design.booster("main").unwrap().thruster.max_thrust, 20.0);
+        assert!(design.booster("retro").is_none());
+    }
+
+    #[test]
+    fn design_should_reject_duplicated_boosters() {
+        let result = SatelliteDesign::parse(
+            r#"(
+                name: "probe",
+                mass: 10.0,
+                size: (1.0, 1.0),
+                sprite: "imgs/probe.png",
+                boosters: [
+                    (name: "main", at: (0.0, 0.5), direction: (0.0, -1.0), thruster: (max_thrust: 20.0)),
+                    (name: "main", at: (0.0, -0.5), direction: (0.0, 1.0), thruster: (max_thrust: 20.0)),
+                ],
+            )"#,
+        );
+
+        assert!(matches!(result, Err(DesignError::Invalid { .. })));
+    }
+
+    #[test]
+    fn design_should_reject_wheel_without_inertia() {
+        let result = SatelliteDesign::parse(
+            r#"(
+                name: "probe",
+                mass: 10.0,
+                size: (1.0, 1.0),
+                sprite: "imgs/probe.png",
+                boosters: [],
+                wheel: Some((max_torque: 1.0, max_momentum: 2.0, inertia: 0.0)),
+            )"#,
+        );
+
+        assert!(matches!(
+            result,
+            Err(DesignError::Invalid { part, .. }) if part == "wheel"
+        ));
+    }
+
+    #[test]
+    fn design_should_reject_non_positive_dimensions() {
+        let parse = |size: &str, sprite_scale: f32, max_thrust: f32| {
+            SatelliteDesign::parse(&format!(
+                r#"(
+                    name: "probe",
+                    mass: 10.0,
+                    size: {size},
+                    sprite: "imgs/probe.png",
+                    sprite_scale: {sprite_scale:?},
+                    boosters: [
+                        (name: "main", at: (0.0, 0.5), direction: (0.0, -1.0), thruster: (max_thrust: {max_thrust:?})),
+                    ],
+                )"#
+            ))
+        };
+
+        assert!(parse("(1.0, 1.0)", 1.0, 20.0).is_ok());
+        assert!(matches!(
+            parse("(1.0, 0.0)", 1.0, 20.0),
+            Err(DesignError::Invalid { part, .. }) if part == "size"
+        ));
+        assert!(matches!(
+            parse("(1.0, 1.0)", 0.0, 20.0),
+            Err(DesignError::Invalid { part, .. }) if part == "sprite_scale"
+        ));
+        assert!(matches!(
+            parse("(1.0, 1.0)", 1.0, -20.0),
+            Err(DesignError::Invalid { part, .. }) if part == "boosters"
+        ));
+    }
+}
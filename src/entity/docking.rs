@@ -0,0 +1,173 @@
+use serde::{Deserialize, Serialize};
+
+use crate::lang::Alignment;
+use crate::theory::geometry::{rotate_vec2, Transform};
+use crate::theory::physics::{Joint, Motion};
+use crate::world::WorldKey;
+
+/// Ports latch once they are this close, in meters...
+pub const LATCH_DISTANCE: f32 = 0.3;
+/// ...facing each other within this angle, in radians (about 10 degrees)...
+pub const LATCH_MISALIGNMENT: f32 = 0.17;
+/// ...and moving relative to each other slower than this, in meters per second.
+pub const LATCH_SPEED: f32 = 0.5;
+
+/// Ports farther than this from any free port read no alignment
+pub const ALIGNMENT_RANGE: f32 = 20.0;
+
+/// Speed the port springs push a satellite away with when it undocks
+pub const UNDOCK_SPEED: f32 = 0.1;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DockingPort {
+    pub name: String,
+    /// In meters from the center of the body
+    pub at: (f32, f32),
+    /// Points out of the body
+    pub direction: (f32, f32),
+}
+
+/// A port latched to a port of another body
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Dock {
+    pub port: String,
+    pub with: WorldKey,
+    pub with_port: String,
+    pub joint: Joint,
+}
+
+/// A port in world space
+#[derive(Clone, Debug)]
+pub struct PlacedPort {
+    pub location: (f32, f32),
+    pub direction: (f32, f32),
+    pub velocity: (f32, f32),
+}
+
+impl DockingPort {
+    pub fn new(name: &str, at: (f32, f32), direction: (f32, f32)) -> Self {
+        Self {
+            name: name.to_string(),
+            at,
+            direction,
+        }
+    }
+
+    pub fn unit_direction(&self) -> (f32, f32) {
+        let (x, y) = self.direction;
+        let length = x.hypot(y);
+
+        (x / length, y / length)
+    }
+
+    /// Where the port is, where it faces and how fast it moves on a body at `transform`
+    /// moving with `motion`.
+    pub fn place(&self, transform: &Transform, motion: &Motion) -> PlacedPort {
+        let offset = rotate_vec2(transform.angle, self.at);
+
+        PlacedPort {
+            location: (
+                transform.location.0 + offset.0,
+                transform.location.1 + offset.1,
+            ),
+            direction: rotate_vec2(transform.angle, self.unit_direction()),
+            velocity: (
+                motion.linear.0 - motion.angular * offset.1,
+                motion.linear.1 + motion.angular * offset.0,
+            ),
+        }
+    }
+
+    /// The frame a latch holds in place. Two latched ports share it, so one of them is
+    /// turned around to face into its body.
+    pub fn frame(&self, turned: bool) -> Transform {
+        let (x, y) = self.direction;
+        let angle = y.atan2(x);
+
+        match turned {
+            true => Transform::new(self.at, angle + std::f32::consts::PI),
+            false => Transform::new(self.at, angle),
+        }
+    }
+}
+
+impl PlacedPort {
+    /// How `other` lines up with this port
+    pub fn alignment(&self, other: &PlacedPort) -> Alignment {
+        let offset = (
+            other.location.0 - self.location.0,
+            other.location.1 - self.location.1,
+        );
+        let distance = offset.0.hypot(offset.1);
+
+        // The ports face each other when `other` points straight against this one
+        let (x, y) = self.direction;
+        let (ox, oy) = (-other.direction.0, -other.direction.1);
+        let misalignment = (x * oy - y * ox).atan2(x * ox + y * oy).abs();
+
+        let relative = (
+            other.velocity.0 - self.velocity.0,
+            other.velocity.1 - self.velocity.1,
+        );
+        let closing_speed = match distance > 0.0 {
+            true => -(relative.0 * offset.0 + relative.1 * offset.1) / distance,
+            false => 0.0,
+        };
+
+        Alignment {
+            distance,
+            misalignment,
+            closing_speed,
+            relative_speed: relative.0.hypot(relative.1),
+        }
+    }
+}
+
+/// Whether two ports lined up like this latch together. They must be closing in, so that
+/// ports pushed apart by undocking do not latch again right away.
+pub fn can_latch(alignment: &Alignment) -> bool {
+    alignment.distance <= LATCH_DISTANCE
+        && alignment.misalignment <= LATCH_MISALIGNMENT
+        && alignment.relative_speed <= LATCH_SPEED
+        && alignment.closing_speed > 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn facing_ports_should_latch_only_while_closing_in() {
+        let station = DockingPort::new("main", (0.0, -1.5), (0.0, -1.0));
+        let satellite = DockingPort::new("dock", (0.0, -0.5), (0.0, -1.0));
+
+        let station = station.place(&Transform::new((0.0, 0.0), 0.0), &Motion::default());
+        let approaching = Motion {
+            linear: (0.0, 0.2),
+            angular: 0.0,
+        };
+
+        // Upside down, right above the station port
+        let above = Transform::new((0.0, -2.1), std::f32::consts::PI);
+        let alignment = station.alignment(&satellite.place(&above, &approaching));
+
+        assert!((alignment.distance - 0.1).abs() < 1e-5);
+        assert!(alignment.misalignment < 1e-5);
+        assert!((alignment.closing_speed - 0.2).abs() < 1e-5);
+        assert!(can_latch(&alignment));
+
+        let leaving = Motion {
+            linear: (0.0, -0.2),
+            angular: 0.0,
+        };
+        assert!(!can_latch(
+            &station.alignment(&satellite.place(&above, &leaving))
+        ));
+
+        // Sideways
+        let sideways = Transform::new((-0.5, -1.6), std::f32::consts::FRAC_PI_2);
+        assert!(!can_latch(
+            &station.alignment(&satellite.place(&sideways, &approaching))
+        ));
+    }
+}
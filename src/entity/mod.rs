@@ -1,17 +1,154 @@
-use ggez::{glam::Vec2, graphics::Canvas, Context, GameResult};
+use std::any::Any;
+use std::fmt::Debug;
 
-use crate::entity::satellite::Satellite;
+use ggez::{glam::Vec2, graphics::Canvas, GameResult};
+use serde::Serialize;
 
+use crate::entity::docking::DockingPort;
+use crate::lang::ProgramClient;
 use crate::system::state::GameState;
 use crate::theory::geometry::Transform;
-use crate::theory::physics::{Physics, PhysicsController, RigidBodyProperty};
+use crate::theory::physics::{
+    Contact, JointProperty, Motion, PhysicalWorld, Physics, PhysicsController, RigidBodyProperty,
+};
 
+pub mod asteroid;
+pub mod damage;
+pub mod design;
+pub mod docking;
+pub mod modular;
+pub mod payload;
+pub mod planet;
+pub mod radio;
+pub mod registry;
 pub mod satellite;
+pub mod sensor;
+pub mod station;
+pub mod thruster;
+pub mod waypoint;
+pub mod wheel;
 
-pub trait Entity {
-    fn update(&mut self, ctx: &mut Context) -> GameResult;
+bitflags::bitflags! {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct Capabilities: u8 {
+        const DRAWABLE     = 0b00001;
+        const RIGID_BODY   = 0b00010;
+        const ASSEMBLY     = 0b00100;
+        const PROGRAMMABLE = 0b01000;
+        const DOCKABLE     = 0b10000;
+    }
+}
+
+/// Anything that lives in the world. Entities opt into each capability by overriding its
+/// accessor, and list the same capabilities in `EntityKind::CAPABILITIES`; debug builds
+/// check that the two agree whenever an entity enters the world.
+pub trait Entity: AnyEntity + Debug + Send {
+    fn update(&mut self) -> GameResult {
+        Ok(())
+    }
+
+    fn as_drawable(&self) -> Option<&dyn Drawable> {
+        None
+    }
+
+    fn as_rigidbody(&mut self) -> Option<&mut dyn RigidBody> {
+        None
+    }
+
+    fn as_assembly(&mut self) -> Option<&mut dyn Assembly> {
+        None
+    }
+
+    fn as_client(&mut self) -> Option<&mut (dyn ProgramClient + Send)> {
+        None
+    }
+
+    fn as_dockable(&self) -> Option<&dyn Dockable> {
+        None
+    }
+}
+
+pub trait EntityKind {
+    const NAME: &'static str;
+    const CAPABILITIES: Capabilities;
+}
+
+/// Implemented for every `Entity + EntityKind + Clone + Serialize`, so that boxed entities
+/// can be cloned, saved, downcast and asked for their kind.
+pub trait AnyEntity {
+    fn kind(&self) -> &'static str;
+    fn capabilities(&self) -> Capabilities;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn clone_boxed(&self) -> Box<dyn Entity>;
+    /// The state of the entity, to be written in any serde format
+    fn as_serialize(&self) -> &dyn erased_serde::Serialize;
+}
+
+impl<T> AnyEntity for T
+where
+    T: Entity + EntityKind + Clone + Serialize + 'static,
+{
+    fn kind(&self) -> &'static str {
+        T::NAME
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        T::CAPABILITIES
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_boxed(&self) -> Box<dyn Entity> {
+        Box::new(self.clone())
+    }
+
+    fn as_serialize(&self) -> &dyn erased_serde::Serialize {
+        self
+    }
+}
+
+impl dyn Entity {
+    pub fn is<T: Entity + 'static>(&self) -> bool {
+        self.as_any().is::<T>()
+    }
+
+    pub fn downcast_ref<T: Entity + 'static>(&self) -> Option<&T> {
+        self.as_any().downcast_ref()
+    }
+
+    pub fn downcast_mut<T: Entity + 'static>(&mut self) -> Option<&mut T> {
+        self.as_any_mut().downcast_mut()
+    }
+
+    /// The capabilities the accessors actually provide, which should be those the kind
+    /// declares.
+    pub fn provided_capabilities(&mut self) -> Capabilities {
+        let mut provided = Capabilities::empty();
+        provided.set(Capabilities::DRAWABLE, self.as_drawable().is_some());
+        provided.set(Capabilities::RIGID_BODY, self.as_rigidbody().is_some());
+        provided.set(Capabilities::ASSEMBLY, self.as_assembly().is_some());
+        provided.set(Capabilities::PROGRAMMABLE, self.as_client().is_some());
+        provided.set(Capabilities::DOCKABLE, self.as_dockable().is_some());
+
+        provided
+    }
+}
+
+impl Clone for Box<dyn Entity> {
+    fn clone(&self) -> Self {
+        self.clone_boxed()
+    }
+}
+
+pub trait Drawable {
     fn draw(&self, canvas: &mut Canvas, state: &GameState) -> GameResult<DrawInstruction>;
-    fn typed(self) -> TypedEntity;
 }
 
 pub trait RigidBody {
@@ -19,7 +156,26 @@ pub trait RigidBody {
     fn register_physics(&mut self, physics: Physics);
     fn get_mut_physics(&mut self) -> &mut Physics;
     fn update_physics(&mut self, controller: &mut PhysicsController);
-    fn report_transform(&mut self, transform: Transform);
+    fn report_transform(&mut self, transform: Transform, motion: Motion);
+    fn sense(&mut self, _world: &PhysicalWorld) {}
+    fn impact(&mut self, _contact: &Contact) {}
+}
+
+pub trait Assembly {
+    fn get_properties(&self) -> (Vec<RigidBodyProperty>, Vec<JointProperty>);
+    fn register_physics(&mut self, physics: Vec<Physics>);
+    /// The body of each part that has one, along with the index of the part
+    fn get_physics(&self) -> Vec<(usize, Physics)>;
+    fn update_part_physics(&mut self, part: usize, controller: &mut PhysicsController);
+    fn report_part_transform(&mut self, part: usize, transform: Transform, motion: Motion);
+    fn impact_part(&mut self, _part: usize, _contact: &Contact) {}
+}
+
+/// A body with docking ports that other bodies can latch onto
+pub trait Dockable {
+    fn ports(&self) -> &[DockingPort];
+    fn body(&self) -> Option<Physics>;
+    fn frame(&self) -> (&Transform, &Motion);
 }
 
 #[derive(Debug, Default)]
@@ -29,6 +185,8 @@ pub enum DrawOrigin {
     ScreenAbsolute,
 }
 
+/// `position` is the center of the drawing in meters for `DrawOrigin::World`, or the
+/// top-left corner in pixels for `DrawOrigin::ScreenAbsolute`. `size` is always in pixels.
 #[derive(Default, Debug)]
 pub struct DrawInstruction {
     pub position: Vec2,
@@ -36,28 +194,3 @@ pub struct DrawInstruction {
     pub angle: f32,
     pub draw_origin: DrawOrigin,
 }
-
-#[derive(Debug)]
-pub enum TypedEntity {
-    Satellite(Satellite),
-}
-
-impl TypedEntity {
-    pub fn inner(&self) -> &dyn Entity {
-        match self {
-            TypedEntity::Satellite(inner) => inner,
-        }
-    }
-
-    pub fn inner_mut(&mut self) -> &mut dyn Entity {
-        match self {
-            TypedEntity::Satellite(inner) => inner,
-        }
-    }
-
-    pub fn as_mut_rigidbody(&mut self) -> Option<&mut dyn RigidBody> {
-        match self {
-            TypedEntity::Satellite(inner) => Some(inner),
-        }
-    }
-}
@@ -0,0 +1,497 @@
+use std::collections::HashMap;
+
+use ggez::{
+    glam::Vec2,
+    graphics::{self, Color},
+};
+use serde::{Deserialize, Serialize};
+
+use super::damage::DamageSpec;
+use super::design::DesignError;
+use super::thruster::{Thruster, ThrusterSpec};
+use super::{Assembly, Capabilities, DrawInstruction, Drawable, Entity, EntityKind};
+use crate::lang::{ClientError, ProgramClient, Telemetry};
+use crate::system::state::GameState;
+use crate::theory::geometry::{rotate_vec2, Transform};
+use crate::theory::physics::{
+    BodyKind, ColliderShape, Contact, JointKind, JointProperty, Motion, Physics, PhysicsController,
+    RigidBodyProperty, TIMESTEP,
+};
+use crate::theory::units::to_pixels;
+
+const THRUSTER: ThrusterSpec = ThrusterSpec {
+    max_thrust: 400.0,
+    spool_up: 0.2,
+    spool_down: 0.1,
+    min_impulse_bit: 2.0,
+    bang_bang: false,
+};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PartBooster {
+    pub name: String,
+    pub at: (f32, f32),
+    pub direction: (f32, f32),
+    pub thruster: Thruster,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CraftPart {
+    pub name: String,
+    pub mass: f32,
+    pub size: (f32, f32),
+    pub offset: (f32, f32),
+    pub boosters: Vec<PartBooster>,
+    pub physics: Option<Physics>,
+    pub transform: Transform,
+    pub motion: Motion,
+    pub health: f32,
+}
+
+/// Parts joined together. The first part is the bus, which the craft is placed and
+/// reported by, so there is always at least one.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "UncheckedCraft")]
+pub struct ModularCraft {
+    pub parts: Vec<CraftPart>,
+    pub joints: Vec<JointProperty>,
+    pub damage: DamageSpec,
+    /// Distance to the nearest body of each kind, as of the last tick
+    #[serde(default)]
+    pub proximity: HashMap<String, Option<f32>>,
+}
+
+/// A craft as read from a file, before its joints are checked against its parts
+#[derive(Deserialize)]
+struct UncheckedCraft {
+    parts: Vec<CraftPart>,
+    joints: Vec<JointProperty>,
+    damage: DamageSpec,
+    #[serde(default)]
+    proximity: HashMap<String, Option<f32>>,
+}
+
+impl PartBooster {
+    pub fn new(name: &str, at: (f32, f32), direction: (f32, f32)) -> Self {
+        Self {
+            name: name.to_string(),
+            at,
+            direction,
+            thruster: Thruster::new(THRUSTER),
+        }
+    }
+}
+
+impl CraftPart {
+    pub fn new(name: &str, mass: f32, size: (f32, f32), offset: (f32, f32)) -> Self {
+        Self {
+            name: name.to_string(),
+            mass,
+            size,
+            offset,
+            boosters: Vec::new(),
+            physics: None,
+            transform: Transform::default(),
+            motion: Motion::default(),
+            health: 1.0,
+        }
+    }
+
+    pub fn with_booster(mut self, booster: PartBooster) -> Self {
+        self.boosters.push(booster);
+        self
+    }
+}
+
+impl ModularCraft {
+    pub fn new(
+        transform: Transform,
+        parts: Vec<CraftPart>,
+        joints: Vec<JointProperty>,
+    ) -> Result<Self, DesignError> {
+        let mut craft = Self::try_from(UncheckedCraft {
+            parts,
+            joints,
+            damage: DamageSpec::default(),
+            proximity: HashMap::new(),
+        })?;
+
+        craft.parts.iter_mut().for_each(|part| {
+            let offset = rotate_vec2(transform.angle, part.offset);
+            part.transform = Transform::new(
+                (
+                    transform.location.0 + offset.0,
+                    transform.location.1 + offset.1,
+                ),
+                transform.angle,
+            );
+        });
+
+        Ok(craft)
+    }
+
+    pub fn sample(transform: Transform) -> Self {
+        Self::new(
+            transform,
+            vec![
+                CraftPart::new("bus", 600.0, (1.6, 1.6), (0.0, 0.0))
+                    .with_booster(PartBooster::new("main", (0.0, 0.8), (0.0, -1.0)))
+                    .with_booster(PartBooster::new("retro", (0.0, -0.8), (0.0, 1.0))),
+                CraftPart::new("wing_l", 100.0, (2.0, 0.6), (-1.8, 0.0))
+                    .with_booster(PartBooster::new("wing_l", (0.0, 0.3), (0.0, -1.0))),
+                CraftPart::new("wing_r", 100.0, (2.0, 0.6), (1.8, 0.0))
+                    .with_booster(PartBooster::new("wing_r", (0.0, 0.3), (0.0, -1.0))),
+                CraftPart::new("boom", 50.0, (0.24, 1.8), (0.0, -1.7)),
+            ],
+            vec![
+                JointProperty {
+                    parts: (0, 1),
+                    anchors: ((-0.8, 0.0), (1.0, 0.0)),
+                    kind: JointKind::Fixed,
+                },
+                JointProperty {
+                    parts: (0, 2),
+                    anchors: ((0.8, 0.0), (-1.0, 0.0)),
+                    kind: JointKind::Fixed,
+                },
+                JointProperty {
+                    parts: (0, 3),
+                    anchors: ((0.0, -0.8), (0.0, 0.9)),
+                    kind: JointKind::Revolute {
+                        limits: Some((-0.5, 0.5)),
+                    },
+                },
+            ],
+        )
+        .unwrap()
+    }
+
+    /// Sets every part moving along with the first one, as if the craft were rigid.
+    pub fn set_motion(&mut self, motion: Motion) {
+        let origin = self.parts[0].transform.location;
+
+        self.parts.iter_mut().for_each(|part| {
+            let offset = (
+                part.transform.location.0 - origin.0,
+                part.transform.location.1 - origin.1,
+            );
+
+            part.motion = Motion {
+                linear: (
+                    motion.linear.0 - motion.angular * offset.1,
+                    motion.linear.1 + motion.angular * offset.0,
+                ),
+                angular: motion.angular,
+            };
+        });
+    }
+
+    fn find_booster(&self, name: &str) -> Option<&PartBooster> {
+        self.parts
+            .iter()
+            .flat_map(|part| part.boosters.iter())
+            .find(|booster| booster.name == name)
+    }
+
+    fn find_booster_mut(&mut self, name: &str) -> Option<&mut PartBooster> {
+        self.parts
+            .iter_mut()
+            .flat_map(|part| part.boosters.iter_mut())
+            .find(|booster| booster.name == name)
+    }
+
+    fn extent(&self) -> f32 {
+        self.parts
+            .iter()
+            .map(|part| part.offset.0.hypot(part.offset.1) + part.size.0.hypot(part.size.1) / 2.0)
+            .fold(0.0, f32::max)
+    }
+}
+
+impl TryFrom<UncheckedCraft> for ModularCraft {
+    type Error = DesignError;
+
+    fn try_from(craft: UncheckedCraft) -> Result<Self, Self::Error> {
+        let invalid = |part: &str, reason: String| DesignError::Invalid {
+            part: part.to_string(),
+            reason,
+        };
+
+        if craft.parts.is_empty() {
+            return Err(invalid(
+                "parts",
+                "a craft needs at least one part".to_string(),
+            ));
+        }
+
+        for JointProperty { parts: (a, b), .. } in &craft.joints {
+            if *a.max(b) >= craft.parts.len() {
+                return Err(invalid(
+                    "joints",
+                    format!(
+                        "Joint between parts {a} and {b} of a craft with {} parts",
+                        craft.parts.len()
+                    ),
+                ));
+            }
+
+            if a == b {
+                return Err(invalid("joints", format!("Joint from part {a} to itself")));
+            }
+        }
+
+        Ok(Self {
+            parts: craft.parts,
+            joints: craft.joints,
+            damage: craft.damage,
+            proximity: craft.proximity,
+        })
+    }
+}
+
+impl EntityKind for ModularCraft {
+    const NAME: &'static str = "modular_craft";
+    const CAPABILITIES: Capabilities = Capabilities::DRAWABLE
+        .union(Capabilities::ASSEMBLY)
+        .union(Capabilities::PROGRAMMABLE);
+}
+
+impl Entity for ModularCraft {
+    fn as_drawable(&self) -> Option<&dyn Drawable> {
+        Some(self)
+    }
+
+    fn as_assembly(&mut self) -> Option<&mut dyn Assembly> {
+        Some(self)
+    }
+
+    fn as_client(&mut self) -> Option<&mut (dyn ProgramClient + Send)> {
+        Some(self)
+    }
+}
+
+impl Drawable for ModularCraft {
+    fn draw(
+        &self,
+        canvas: &mut graphics::Canvas,
+        _state: &GameState,
+    ) -> ggez::GameResult<DrawInstruction> {
+        let extent = to_pixels(self.extent());
+        let origin = &self.parts[0].transform;
+
+        self.parts.iter().for_each(|part| {
+            let relative = rotate_vec2(
+                -origin.angle,
+                (
+                    part.transform.location.0 - origin.location.0,
+                    part.transform.location.1 - origin.location.1,
+                ),
+            );
+
+            canvas.draw(
+                &graphics::Quad,
+                graphics::DrawParam::new()
+                    .dest(Vec2::new(
+                        extent + to_pixels(relative.0),
+                        extent + to_pixels(relative.1),
+                    ))
+                    .offset(Vec2::new(0.5, 0.5))
+                    .scale(Vec2::new(to_pixels(part.size.0), to_pixels(part.size.1)))
+                    .rotation(part.transform.angle - origin.angle)
+                    .color(Color::from_rgb(180, 180, 190)),
+            );
+        });
+
+        Ok(DrawInstruction {
+            position: origin.location.into(),
+            angle: origin.angle,
+            size: Vec2::new(extent * 2.0, extent * 2.0),
+            ..Default::default()
+        })
+    }
+}
+
+impl Assembly for ModularCraft {
+    fn get_properties(&self) -> (Vec<RigidBodyProperty>, Vec<JointProperty>) {
+        let parts = self
+            .parts
+            .iter()
+            .map(|part| RigidBodyProperty {
+                kind: BodyKind::Dynamic,
+                mass: part.mass,
+                shape: ColliderShape::Cuboid {
+                    width: part.size.0,
+                    height: part.size.1,
+                },
+                initial_transform: part.transform.clone(),
+                initial_motion: part.motion.clone(),
+            })
+            .collect();
+
+        (parts, self.joints.clone())
+    }
+
+    fn register_physics(&mut self, physics: Vec<Physics>) {
+        self.parts
+            .iter_mut()
+            .zip(physics)
+            .for_each(|(part, physics)| part.physics = Some(physics));
+    }
+
+    fn get_physics(&self) -> Vec<(usize, Physics)> {
+        self.parts
+            .iter()
+            .enumerate()
+            .filter_map(|(index, part)| Some((index, part.physics?)))
+            .collect()
+    }
+
+    fn update_part_physics(&mut self, part: usize, controller: &mut PhysicsController) {
+        self.parts[part].boosters.iter_mut().for_each(|booster| {
+            let force = booster.thruster.step(TIMESTEP);
+
+            controller.apply_force_locally(
+                booster.at,
+                (booster.direction.0 * force, booster.direction.1 * force),
+            );
+        });
+    }
+
+    fn report_part_transform(&mut self, part: usize, transform: Transform, motion: Motion) {
+        self.parts[part].transform = transform;
+        self.parts[part].motion = motion;
+    }
+
+    fn impact_part(&mut self, part: usize, contact: &Contact) {
+        let damage = self.damage.damage(contact.impulse);
+        if damage <= 0.0 {
+            return;
+        }
+
+        let part = &mut self.parts[part];
+        let health = (part.health - damage).max(0.0);
+
+        part.health = health;
+        part.boosters
+            .iter_mut()
+            .for_each(|booster| booster.thruster.set_health(health));
+    }
+}
+
+impl ProgramClient for ModularCraft {
+    fn is_valid_booster(&self, name: &str) -> bool {
+        self.parts
+            .iter()
+            .flat_map(|part| part.boosters.iter())
+            .any(|booster| booster.name == name)
+    }
+
+    fn boost(&mut self, location: &str, power: f32) -> Result<(), ClientError> {
+        if !(0.0..=1.0).contains(&power) {
+            return Err(ClientError::ValidationFailure {
+                performing: "boosting".to_string(),
+                part: "power".to_string(),
+                reason: "power should be in between 0 - 1".to_string(),
+            });
+        }
+
+        let Some(booster) = self.find_booster_mut(location) else {
+            return Err(ClientError::ValidationFailure {
+                performing: "boosting".to_string(),
+                part: "location".to_string(),
+                reason: format!("Unknown booster ({location})"),
+            });
+        };
+
+        booster.thruster.set_command(power);
+
+        Ok(())
+    }
+
+    fn thrust(&self, location: &str) -> Result<f32, ClientError> {
+        self.find_booster(location)
+            .map(|booster| booster.thruster.thrust())
+            .ok_or_else(|| ClientError::ValidationFailure {
+                performing: "reading thrust".to_string(),
+                part: "location".to_string(),
+                reason: format!("Unknown booster ({location})"),
+            })
+    }
+
+    fn damage(&self, part: &str) -> Result<f32, ClientError> {
+        self.parts
+            .iter()
+            .find(|candidate| candidate.name == part)
+            .map(|part| 1.0 - part.health)
+            .ok_or_else(|| ClientError::ValidationFailure {
+                performing: "reading damage".to_string(),
+                part: "part".to_string(),
+                reason: format!("Unknown part ({part})"),
+            })
+    }
+
+    fn nearest(&self, kind: &str) -> Result<Option<f32>, ClientError> {
+        self.proximity
+            .get(kind)
+            .copied()
+            .ok_or_else(|| ClientError::ValidationFailure {
+                performing: "sensing proximity".to_string(),
+                part: "kind".to_string(),
+                reason: format!("Unknown kind ({kind})"),
+            })
+    }
+
+    fn report_proximity(&mut self, proximity: HashMap<String, Option<f32>>) {
+        self.proximity = proximity;
+    }
+
+    fn telemetry(&self) -> Telemetry {
+        let bus = &self.parts[0];
+
+        Telemetry {
+            position: bus.transform.location,
+            velocity: bus.motion.linear,
+            angle: bus.transform.angle,
+            angular_velocity: bus.motion.angular,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn craft_should_reject_joints_to_missing_parts() {
+        let part = || CraftPart::new("bus", 100.0, (1.0, 1.0), (0.0, 0.0));
+        let joint = |parts| JointProperty {
+            parts,
+            anchors: ((0.0, 0.0), (0.0, 0.0)),
+            kind: JointKind::Fixed,
+        };
+
+        assert!(matches!(
+            ModularCraft::new(Transform::default(), Vec::new(), Vec::new()),
+            Err(DesignError::Invalid { part, .. }) if part == "parts"
+        ));
+        assert!(matches!(
+            ModularCraft::new(Transform::default(), vec![part(), part()], vec![joint((0, 2))]),
+            Err(DesignError::Invalid { part, .. }) if part == "joints"
+        ));
+        assert!(ModularCraft::new(
+            Transform::default(),
+            vec![part(), part()],
+            vec![joint((0, 1))]
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn craft_should_reject_a_saved_state_without_parts() {
+        let mut craft = ModularCraft::sample(Transform::default());
+        craft.parts.clear();
+
+        let source = ron::to_string(&craft).unwrap();
+        assert!(ron::from_str::<ModularCraft>(&source).is_err());
+    }
+}
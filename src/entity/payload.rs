@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+
+use crate::theory::geometry::{rotate_vec2, Transform};
+use crate::theory::physics::Motion;
+
+/// Something a satellite carries and can eject, like a cubesat, a probe or a decoy. The
+/// payload itself is a satellite built from the design named `design`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PayloadDesign {
+    pub name: String,
+    pub design: String,
+    /// Mount point on the carrier, in meters
+    pub at: (f32, f32),
+    pub direction: (f32, f32),
+    /// Impulse pushing the payload away, in newton-seconds
+    pub impulse: f32,
+    #[serde(default = "default_count")]
+    pub count: u32,
+}
+
+/// A payload the firmware asked to eject, waiting for the simulation to spawn it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Deployment {
+    pub payload: String,
+    pub firmware: Option<String>,
+}
+
+fn default_count() -> u32 {
+    1
+}
+
+impl PayloadDesign {
+    /// Impulse on the payload in the carrier's frame; the carrier takes the opposite.
+    pub fn local_impulse(&self) -> (f32, f32) {
+        let (x, y) = self.direction;
+        let length = x.hypot(y);
+
+        (x / length * self.impulse, y / length * self.impulse)
+    }
+
+    /// Where the payload starts and how it moves when ejected from a carrier at
+    /// `transform` moving with `motion`. It keeps the velocity of its mount point and
+    /// gains the ejection impulse over its own mass.
+    pub fn eject(&self, transform: &Transform, motion: &Motion, mass: f32) -> (Transform, Motion) {
+        let offset = rotate_vec2(transform.angle, self.at);
+        let impulse = rotate_vec2(transform.angle, self.local_impulse());
+
+        let location = (
+            transform.location.0 + offset.0,
+            transform.location.1 + offset.1,
+        );
+        let linear = (
+            motion.linear.0 - motion.angular * offset.1 + impulse.0 / mass,
+            motion.linear.1 + motion.angular * offset.0 + impulse.1 / mass,
+        );
+
+        (
+            Transform::new(location, transform.angle),
+            Motion {
+                linear,
+                angular: motion.angular,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload_should_keep_carrier_velocity_and_gain_impulse() {
+        let payload = PayloadDesign {
+            name: "probe".to_string(),
+            design: "probe".to_string(),
+            at: (0.0, 1.0),
+            direction: (0.0, 2.0),
+            impulse: 10.0,
+            count: 1,
+        };
+        let carrier = Transform::new((5.0, 0.0), std::f32::consts::FRAC_PI_2);
+        let motion = Motion {
+            linear: (1.0, 0.0),
+            angular: 0.5,
+        };
+
+        let (transform, motion) = payload.eject(&carrier, &motion, 5.0);
+
+        // The mount point ends up at (-1, 0) from the carrier, which spins it towards -y
+        assert!((transform.location.0 - 4.0).abs() < 1e-5);
+        assert!(transform.location.1.abs() < 1e-5);
+        assert!((motion.linear.0 - (1.0 - 2.0)).abs() < 1e-5);
+        assert!((motion.linear.1 + 0.5).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,112 @@
+use ggez::{
+    glam::Vec2,
+    graphics::{self, Color},
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{Capabilities, DrawInstruction, Drawable, Entity, EntityKind, RigidBody};
+use crate::system::state::GameState;
+use crate::theory::geometry::Transform;
+use crate::theory::physics::{
+    BodyKind, ColliderShape, Motion, Physics, PhysicsController, RigidBodyProperty,
+};
+use crate::theory::units::to_pixels;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Planet {
+    pub physics: Option<Physics>,
+    pub transform: Transform,
+    pub radius: f32,
+    #[serde(
+        serialize_with = "serialize_color",
+        deserialize_with = "deserialize_color"
+    )]
+    pub color: Color,
+}
+
+fn serialize_color<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+    <[f32; 4]>::from(*color).serialize(serializer)
+}
+
+fn deserialize_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    <[f32; 4]>::deserialize(deserializer).map(Color::from)
+}
+
+impl Planet {
+    pub fn new(location: (f32, f32), radius: f32, color: Color) -> Self {
+        Self {
+            physics: None,
+            transform: Transform::new(location, 0.0),
+            radius,
+            color,
+        }
+    }
+}
+
+impl EntityKind for Planet {
+    const NAME: &'static str = "planet";
+    const CAPABILITIES: Capabilities = Capabilities::DRAWABLE.union(Capabilities::RIGID_BODY);
+}
+
+impl Entity for Planet {
+    fn as_drawable(&self) -> Option<&dyn Drawable> {
+        Some(self)
+    }
+
+    fn as_rigidbody(&mut self) -> Option<&mut dyn RigidBody> {
+        Some(self)
+    }
+}
+
+impl Drawable for Planet {
+    fn draw(
+        &self,
+        canvas: &mut graphics::Canvas,
+        state: &GameState,
+    ) -> ggez::GameResult<DrawInstruction> {
+        let radius = to_pixels(self.radius);
+
+        canvas.draw(
+            &state.circle,
+            graphics::DrawParam::new()
+                .dest(Vec2::new(radius, radius))
+                .scale(Vec2::new(radius, radius))
+                .color(self.color),
+        );
+
+        Ok(DrawInstruction {
+            position: self.transform.location.into(),
+            angle: self.transform.angle,
+            size: Vec2::new(radius * 2.0, radius * 2.0),
+            ..Default::default()
+        })
+    }
+}
+
+impl RigidBody for Planet {
+    fn get_property(&self) -> RigidBodyProperty {
+        RigidBodyProperty {
+            kind: BodyKind::Fixed,
+            mass: 0.0,
+            shape: ColliderShape::Ball {
+                radius: self.radius,
+            },
+            initial_transform: self.transform.clone(),
+            initial_motion: Motion::default(),
+        }
+    }
+
+    fn register_physics(&mut self, physics: Physics) {
+        self.physics = Some(physics);
+    }
+
+    fn get_mut_physics(&mut self) -> &mut Physics {
+        self.physics.as_mut().unwrap()
+    }
+
+    fn update_physics(&mut self, _controller: &mut PhysicsController) {}
+
+    fn report_transform(&mut self, transform: Transform, _motion: Motion) {
+        self.transform = transform;
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+/// Messages waiting in the inbox past this many are dropped, oldest first
+const INBOX_CAPACITY: usize = 64;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RadioSpec {
+    /// Farthest receiver the transmitter reaches, in meters
+    pub range: f32,
+    /// Extra ticks a message spends on its way; without any, it arrives on the tick after
+    /// it was sent
+    #[serde(default)]
+    pub latency: u64,
+    /// Bytes the transmitter can send per tick
+    pub bandwidth: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RadioMessage {
+    pub channel: String,
+    pub message: String,
+}
+
+impl RadioMessage {
+    fn size(&self) -> usize {
+        self.channel.len() + self.message.len()
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Radio {
+    pub spec: RadioSpec,
+    /// Bytes sent during the current tick
+    sent: usize,
+    outbox: Vec<RadioMessage>,
+    /// Messages on their way in, with the ticks left before they arrive
+    incoming: Vec<(u64, RadioMessage)>,
+    inbox: VecDeque<RadioMessage>,
+}
+
+impl Radio {
+    pub fn new(spec: RadioSpec) -> Self {
+        Self {
+            spec,
+            sent: 0,
+            outbox: Vec::new(),
+            incoming: Vec::new(),
+            inbox: VecDeque::new(),
+        }
+    }
+
+    /// Queues the message for transmission, unless it does not fit in what is left of this
+    /// tick's bandwidth.
+    pub fn send(&mut self, message: RadioMessage) -> bool {
+        if self.sent + message.size() > self.spec.bandwidth {
+            return false;
+        }
+
+        self.sent += message.size();
+        self.outbox.push(message);
+
+        true
+    }
+
+    pub fn receive(&mut self) -> Option<RadioMessage> {
+        self.inbox.pop_front()
+    }
+
+    /// Takes the messages sent during this tick, and frees the bandwidth for the next one.
+    pub fn transmit(&mut self) -> Vec<RadioMessage> {
+        self.sent = 0;
+
+        std::mem::take(&mut self.outbox)
+    }
+
+    pub fn deliver(&mut self, message: RadioMessage, latency: u64) {
+        self.incoming.push((latency, message));
+    }
+
+    /// Moves the messages in flight a tick closer, and the ones that arrived to the inbox.
+    pub fn tick(&mut self) {
+        let (arrived, incoming): (Vec<_>, Vec<_>) = std::mem::take(&mut self.incoming)
+            .into_iter()
+            .partition(|(ticks_left, _)| *ticks_left == 0);
+
+        self.incoming = incoming
+            .into_iter()
+            .map(|(ticks_left, message)| (ticks_left - 1, message))
+            .collect();
+
+        for (_, message) in arrived {
+            if self.inbox.len() == INBOX_CAPACITY {
+                self.inbox.pop_front();
+            }
+            self.inbox.push_back(message);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(text: &str) -> RadioMessage {
+        RadioMessage {
+            channel: "ch".to_string(),
+            message: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn radio_should_cap_bandwidth_per_tick() {
+        let mut radio = Radio::new(RadioSpec {
+            range: 100.0,
+            latency: 0,
+            bandwidth: 8,
+        });
+
+        assert!(radio.send(message("abc")));
+        assert!(!radio.send(message("abcd")));
+        assert!(radio.send(message("a")));
+        assert_eq!(radio.transmit().len(), 2);
+
+        assert!(radio.send(message("abcd")));
+    }
+
+    #[test]
+    fn radio_should_hold_messages_for_latency() {
+        let mut radio = Radio::new(RadioSpec {
+            range: 100.0,
+            latency: 2,
+            bandwidth: 8,
+        });
+
+        radio.deliver(message("hi"), 2);
+        radio.tick();
+        radio.tick();
+        assert_eq!(radio.receive(), None);
+
+        radio.tick();
+        assert_eq!(radio.receive(), Some(message("hi")));
+        assert_eq!(radio.receive(), None);
+    }
+}
@@ -0,0 +1,180 @@
+use std::any::TypeId;
+use std::collections::HashMap;
+
+use serde::de::DeserializeOwned;
+use serde::Deserializer;
+
+use super::asteroid::Asteroid;
+use super::modular::ModularCraft;
+use super::planet::Planet;
+use super::satellite::Satellite;
+use super::station::Station;
+use super::waypoint::Waypoint;
+use super::{Capabilities, Entity, EntityKind};
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum RegistryError {
+    #[error("Entity kind '{0}' is already registered")]
+    Duplicated(&'static str),
+
+    #[error("Entity kind '{0}' is not registered")]
+    Unknown(String),
+
+    #[error("Could not load an entity of kind '{kind}': {reason}")]
+    Corrupted { kind: String, reason: String },
+}
+
+#[derive(Clone, Debug)]
+pub struct RegisteredKind {
+    pub name: &'static str,
+    pub capabilities: Capabilities,
+    type_id: TypeId,
+    load: fn(&mut dyn erased_serde::Deserializer) -> Result<Box<dyn Entity>, erased_serde::Error>,
+}
+
+/// Every kind of entity the world knows about. The built-in kinds are registered by
+/// default, and other crates can add their own with `register`.
+#[derive(Clone, Debug)]
+pub struct EntityRegistry {
+    kinds: HashMap<&'static str, RegisteredKind>,
+}
+
+impl Default for EntityRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+
+        registry.register::<Satellite>().unwrap();
+        registry.register::<ModularCraft>().unwrap();
+        registry.register::<Planet>().unwrap();
+        registry.register::<Asteroid>().unwrap();
+        registry.register::<Station>().unwrap();
+        registry.register::<Waypoint>().unwrap();
+
+        registry
+    }
+}
+
+impl EntityRegistry {
+    pub fn empty() -> Self {
+        Self {
+            kinds: HashMap::new(),
+        }
+    }
+
+    pub fn register<T>(&mut self) -> Result<(), RegistryError>
+    where
+        T: Entity + EntityKind + DeserializeOwned + 'static,
+    {
+        if self.kinds.contains_key(T::NAME) {
+            return Err(RegistryError::Duplicated(T::NAME));
+        }
+
+        self.kinds.insert(
+            T::NAME,
+            RegisteredKind {
+                name: T::NAME,
+                capabilities: T::CAPABILITIES,
+                type_id: TypeId::of::<T>(),
+                load: |state| Ok(Box::new(erased_serde::deserialize::<T>(state)?)),
+            },
+        );
+
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&RegisteredKind> {
+        self.kinds.get(name)
+    }
+
+    pub fn kind_of(&self, entity: &dyn Entity) -> Option<&RegisteredKind> {
+        self.kinds
+            .get(entity.kind())
+            .filter(|kind| kind.type_id == entity.as_any().type_id())
+    }
+
+    /// Rebuilds an entity of `kind` from the state `AnyEntity::as_serialize` wrote for it.
+    pub fn load<'de, D>(&self, kind: &str, state: D) -> Result<Box<dyn Entity>, RegistryError>
+    where
+        D: Deserializer<'de>,
+    {
+        let registered = self
+            .kinds
+            .get(kind)
+            .ok_or_else(|| RegistryError::Unknown(kind.to_string()))?;
+
+        (registered.load)(&mut <dyn erased_serde::Deserializer>::erase(state)).map_err(|err| {
+            RegistryError::Corrupted {
+                kind: kind.to_string(),
+                reason: err.to_string(),
+            }
+        })
+    }
+
+    pub fn kinds_with(&self, capabilities: Capabilities) -> impl Iterator<Item = &RegisteredKind> {
+        self.kinds
+            .values()
+            .filter(move |kind| kind.capabilities.contains(capabilities))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry_should_find_kinds_by_capability() {
+        let registry = EntityRegistry::default();
+        let mut programmable: Vec<_> = registry
+            .kinds_with(Capabilities::PROGRAMMABLE)
+            .map(|kind| kind.name)
+            .collect();
+        programmable.sort();
+
+        assert_eq!(programmable, vec![ModularCraft::NAME, Satellite::NAME]);
+    }
+
+    #[test]
+    fn registry_should_reject_duplicated_kinds() {
+        let mut registry = EntityRegistry::default();
+
+        assert_eq!(
+            registry.register::<Waypoint>(),
+            Err(RegistryError::Duplicated(Waypoint::NAME))
+        );
+    }
+
+    #[test]
+    fn boxed_entity_should_be_downcast_to_its_kind() {
+        let registry = EntityRegistry::default();
+        let entity: Box<dyn Entity> = Box::new(Waypoint::new("alpha", (0.0, 0.0), 1.0));
+
+        assert_eq!(registry.kind_of(entity.as_ref()).unwrap().name, "waypoint");
+        assert_eq!(entity.downcast_ref::<Waypoint>().unwrap().name, "alpha");
+        assert!(entity.downcast_ref::<Planet>().is_none());
+    }
+
+    #[test]
+    fn saved_entity_should_be_loaded_by_its_kind() {
+        let registry = EntityRegistry::default();
+        let entity: Box<dyn Entity> = Box::new(Waypoint::new("alpha", (1.0, 2.0), 1.5));
+
+        let state = ron::to_string(entity.as_serialize()).unwrap();
+        let loaded = registry
+            .load(
+                entity.kind(),
+                &mut ron::Deserializer::from_str(&state).unwrap(),
+            )
+            .unwrap();
+        assert_eq!(
+            loaded.downcast_ref::<Waypoint>().unwrap().location,
+            (1.0, 2.0)
+        );
+
+        assert_eq!(
+            registry
+                .load("comet", &mut ron::Deserializer::from_str("()").unwrap())
+                .err(),
+            Some(RegistryError::Unknown("comet".to_string()))
+        );
+    }
+}
@@ -1,101 +1,200 @@
-use std::{collections::HashMap, str::FromStr};
+use std::collections::HashMap;
 
 use ggez::{
     glam::Vec2,
     graphics::{self, Color},
-    Context,
+    GameError,
 };
+use serde::{Deserialize, Serialize};
 
-use super::{DrawInstruction, Entity, TypedEntity};
+use super::damage::{apply_impact, PartHealth};
+use super::design::{SatelliteDesign, HULL};
+use super::docking::{Dock, DockingPort, UNDOCK_SPEED};
+use super::payload::Deployment;
+use super::radio::{Radio, RadioMessage};
+use super::sensor::RangeSensor;
+use super::thruster::Thruster;
+use super::wheel::ReactionWheel;
+use super::{Capabilities, Dockable, DrawInstruction, Drawable, Entity, EntityKind};
 use crate::entity::RigidBody;
-use crate::theory::geometry::Transform;
-use crate::theory::physics::{PhysicsController, RigidBodyProperty};
+use crate::theory::geometry::{rotate_vec2, Transform};
+use crate::theory::physics::{
+    BodyKind, ColliderShape, Contact, Motion, PhysicalWorld, PhysicsController, RigidBodyProperty,
+    TIMESTEP,
+};
 use crate::{
-    lang::{ClientError, ProgramClient},
+    lang::{Alignment, ClientError, ProgramClient, Telemetry},
     system::state::GameState,
     theory::physics::Physics,
 };
 
-#[derive(Debug)]
+const SENSOR_RANGE: f32 = 50.0;
+
+/// How far the proximity sensor sees other bodies, in meters
+pub const PROXIMITY_RANGE: f32 = 30.0;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Satellite {
     pub physics: Option<Physics>,
     pub transform: Transform,
-    pub booster: HashMap<SatelliteBoosters, f32>,
-}
-
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
-pub enum SatelliteBoosters {
-    BL,
-    BR,
-    FL,
-    FR,
-    WL,
-    WR,
+    pub motion: Motion,
+    pub shape: ColliderShape,
+    pub design: SatelliteDesign,
+    pub booster: HashMap<String, Thruster>,
+    pub wheel: Option<ReactionWheel>,
+    pub radio: Option<Radio>,
+    pub health: Vec<PartHealth>,
+    pub sensors: Vec<RangeSensor>,
+    /// Distance to the nearest body of each kind within `PROXIMITY_RANGE`, refreshed by
+    /// the simulation every tick
+    pub proximity: HashMap<String, Option<f32>>,
+    /// Payloads left in the bay
+    pub payloads: HashMap<String, u32>,
+    /// Payloads ejected by the firmware, waiting for the simulation to spawn them
+    pub deployments: Vec<Deployment>,
+    /// Ports latched to other bodies, kept up to date by the simulation
+    pub docks: Vec<Dock>,
+    /// How each free port lines up with the nearest free port around, refreshed by the
+    /// simulation every tick
+    pub alignment: HashMap<String, Option<Alignment>>,
+    /// Ports the firmware asked to release, waiting for the simulation to unlatch them
+    pub undocking: Vec<String>,
+    /// Kicks from ejected payloads and undocking, applied on the next physics update
+    recoil: Vec<((f32, f32), (f32, f32))>,
 }
 
 impl Satellite {
-    pub fn new() -> Self {
+    pub fn new(design: SatelliteDesign) -> Self {
+        let (width, height) = design.size;
+
         Self {
             physics: None,
             transform: Transform::default(),
-            booster: HashMap::from([
-                (SatelliteBoosters::BL, 0.0),
-                (SatelliteBoosters::BR, 0.0),
-                (SatelliteBoosters::FL, 0.0),
-                (SatelliteBoosters::FR, 0.0),
-                (SatelliteBoosters::WL, 0.0),
-                (SatelliteBoosters::WR, 0.0),
-            ]),
+            motion: Motion::default(),
+            shape: design
+                .shape
+                .clone()
+                .unwrap_or(ColliderShape::Cuboid { width, height }),
+            booster: design
+                .boosters
+                .iter()
+                .map(|booster| {
+                    (
+                        booster.name.clone(),
+                        Thruster::new(booster.thruster.clone()),
+                    )
+                })
+                .collect(),
+            wheel: design.wheel.clone().map(ReactionWheel::new),
+            radio: design.radio.clone().map(Radio::new),
+            health: std::iter::once(PartHealth::new(HULL, (0.0, 0.0)))
+                .chain(
+                    design
+                        .boosters
+                        .iter()
+                        .map(|booster| PartHealth::new(&booster.name, booster.at)),
+                )
+                .collect(),
+            sensors: vec![
+                RangeSensor::new("front", (0.0, -height / 2.0), (0.0, -1.0), SENSOR_RANGE),
+                RangeSensor::new("rear", (0.0, height / 2.0), (0.0, 1.0), SENSOR_RANGE),
+                RangeSensor::new("left", (-width / 2.0, 0.0), (-1.0, 0.0), SENSOR_RANGE),
+                RangeSensor::new("right", (width / 2.0, 0.0), (1.0, 0.0), SENSOR_RANGE),
+            ],
+            proximity: HashMap::new(),
+            payloads: design
+                .payloads
+                .iter()
+                .map(|payload| (payload.name.clone(), payload.count))
+                .collect(),
+            deployments: Vec::new(),
+            docks: Vec::new(),
+            alignment: HashMap::new(),
+            undocking: Vec::new(),
+            recoil: Vec::new(),
+            design,
         }
     }
-}
 
-impl Default for Satellite {
-    fn default() -> Self {
-        Self::new()
+    pub fn with_sensor(mut self, sensor: RangeSensor) -> Self {
+        self.sensors.retain(|existing| existing.name != sensor.name);
+        self.sensors.push(sensor);
+        self
     }
+
+    pub fn with_shape(mut self, shape: ColliderShape) -> Self {
+        self.shape = shape;
+        self
+    }
+}
+
+impl EntityKind for Satellite {
+    const NAME: &'static str = "satellite";
+    const CAPABILITIES: Capabilities = Capabilities::DRAWABLE
+        .union(Capabilities::RIGID_BODY)
+        .union(Capabilities::PROGRAMMABLE)
+        .union(Capabilities::DOCKABLE);
 }
 
 impl Entity for Satellite {
-    fn update(&mut self, _ctx: &mut Context) -> ggez::GameResult {
-        Ok(())
+    fn as_drawable(&self) -> Option<&dyn Drawable> {
+        Some(self)
     }
 
+    fn as_rigidbody(&mut self) -> Option<&mut dyn RigidBody> {
+        Some(self)
+    }
+
+    fn as_client(&mut self) -> Option<&mut (dyn ProgramClient + Send)> {
+        Some(self)
+    }
+
+    fn as_dockable(&self) -> Option<&dyn Dockable> {
+        Some(self)
+    }
+}
+
+impl Drawable for Satellite {
     fn draw(
         &self,
         canvas: &mut graphics::Canvas,
         state: &GameState,
     ) -> ggez::GameResult<DrawInstruction> {
+        let sprite = state
+            .sprites
+            .get(&self.design.sprite)
+            .ok_or_else(|| GameError::ResourceNotFound(self.design.sprite.clone(), Vec::new()))?;
+        let scale = self.design.sprite_scale;
+
         canvas.draw(
-            &state.satellite_svg,
+            sprite,
             graphics::DrawParam::from(Vec2::new(0.0, 0.0))
                 .color(Color::WHITE)
-                .scale(Vec2::new(0.5, 0.5)),
+                .scale(Vec2::new(scale, scale)),
+        );
+
+        let size = Vec2::new(
+            sprite.width() as f32 * scale,
+            sprite.height() as f32 * scale,
         );
 
         Ok(DrawInstruction {
             position: self.transform.location.into(),
             angle: self.transform.angle,
-            size: (
-                (state.satellite_svg.width() as f32 / 2.0),
-                (state.satellite_svg.height() as f32 / 2.0),
-            )
-                .into(),
+            size,
             ..Default::default()
         })
     }
-
-    fn typed(self) -> TypedEntity {
-        TypedEntity::Satellite(self)
-    }
 }
 
 impl RigidBody for Satellite {
     fn get_property(&self) -> RigidBodyProperty {
         RigidBodyProperty {
-            mass: 1000.0,
-            size: (141.0, 48.0),
+            kind: BodyKind::Dynamic,
+            mass: self.design.mass,
+            shape: self.shape.clone(),
             initial_transform: self.transform.clone(),
+            initial_motion: self.motion.clone(),
         }
     }
 
@@ -108,48 +207,97 @@ impl RigidBody for Satellite {
     }
 
     fn update_physics(&mut self, controller: &mut PhysicsController) {
-        use SatelliteBoosters::*;
+        self.design.boosters.iter().for_each(|booster| {
+            let force = self.booster.get_mut(&booster.name).unwrap().step(TIMESTEP);
 
-        fn relative(x: f32, y: f32) -> (f32, f32) {
-            (141.0 / 2.0 * x, 48.0 / 2.0 * y)
+            controller.apply_force_locally(
+                booster.at,
+                (booster.direction.0 * force, booster.direction.1 * force),
+            );
+        });
+
+        if let Some(wheel) = &mut self.wheel {
+            controller.apply_torque(wheel.step(TIMESTEP));
         }
 
-        let on = |location: SatelliteBoosters| -> f32 {
-            *self.booster.get(&location).unwrap() * 250000.0
+        for (at, impulse) in self.recoil.drain(..) {
+            controller.apply_impulse_locally(at, impulse);
+        }
+    }
+
+    fn report_transform(&mut self, transform: Transform, motion: Motion) {
+        self.transform = transform;
+        self.motion = motion;
+    }
+
+    fn sense(&mut self, world: &PhysicalWorld) {
+        let Some(physics) = &self.physics else {
+            return;
         };
 
-        // BL
-        controller.apply_force_locally(relative(-0.25, 0.0), (0.0, -on(BL)));
+        self.sensors
+            .iter_mut()
+            .for_each(|sensor| sensor.measure(world, &self.transform, physics));
+    }
 
-        // BR
-        controller.apply_force_locally(relative(0.25, 0.0), (0.0, -on(BR)));
+    fn impact(&mut self, contact: &Contact) {
+        let point = rotate_vec2(
+            -self.transform.angle,
+            (
+                contact.point.0 - self.transform.location.0,
+                contact.point.1 - self.transform.location.1,
+            ),
+        );
 
-        // FL
-        controller.apply_force_locally(relative(-0.25, 0.0), (0.0, on(FL)));
+        let Some(part) = apply_impact(
+            &mut self.health,
+            &self.design.damage,
+            point,
+            contact.impulse,
+        ) else {
+            return;
+        };
+
+        if let Some(thruster) = self.booster.get_mut(&part.name) {
+            thruster.set_health(part.health);
+        }
+    }
+}
 
-        // FR
-        controller.apply_force_locally(relative(0.25, 0.0), (0.0, on(FR)));
+impl Dockable for Satellite {
+    fn ports(&self) -> &[DockingPort] {
+        &self.design.ports
+    }
 
-        // WL
-        controller.apply_force_locally(relative(-0.85, 0.0), (0.0, -on(WL)));
+    fn body(&self) -> Option<Physics> {
+        self.physics
+    }
 
-        // WR
-        controller.apply_force_locally(relative(0.85, 0.0), (0.0, -on(WR)));
+    fn frame(&self) -> (&Transform, &Motion) {
+        (&self.transform, &self.motion)
     }
+}
 
-    fn report_transform(&mut self, transform: Transform) {
-        self.transform = transform;
+impl Satellite {
+    fn docking_port(&self, performing: &str, port: &str) -> Result<&DockingPort, ClientError> {
+        self.design
+            .port(port)
+            .ok_or_else(|| ClientError::ValidationFailure {
+                performing: performing.to_string(),
+                part: "port".to_string(),
+                reason: format!("Unknown docking port ({port})"),
+            })
     }
 }
 
 impl ProgramClient for Satellite {
     fn is_valid_booster(&self, name: &str) -> bool {
-        name.parse::<SatelliteBoosters>().is_ok()
+        self.design.booster(name).is_some()
     }
 
     fn boost(&mut self, location: &str, power: f32) -> Result<(), ClientError> {
-        let Ok(booster) = location.parse::<SatelliteBoosters>() else {
-            return Err(ClientError::ValidationFailure{
+        let Some(booster) = self.booster.get_mut(location) else {
+            return Err(ClientError::ValidationFailure {
                 performing: "boosting".to_string(),
                 part: "location".to_string(),
                 reason: format!("Unknown booster ({location})"),
@@ -164,24 +312,192 @@ impl ProgramClient for Satellite {
             });
         }
 
-        self.booster.insert(booster, power);
+        booster.set_command(power);
 
         Ok(())
     }
-}
 
-impl FromStr for SatelliteBoosters {
-    type Err = ();
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "BL" => Ok(Self::BL),
-            "BR" => Ok(Self::BR),
-            "FL" => Ok(Self::FL),
-            "FR" => Ok(Self::FR),
-            "WL" => Ok(Self::WL),
-            "WR" => Ok(Self::WR),
-            _ => Err(()),
+    fn thrust(&self, location: &str) -> Result<f32, ClientError> {
+        self.booster
+            .get(location)
+            .map(Thruster::thrust)
+            .ok_or_else(|| ClientError::ValidationFailure {
+                performing: "reading thrust".to_string(),
+                part: "location".to_string(),
+                reason: format!("Unknown booster ({location})"),
+            })
+    }
+
+    fn wheel(&mut self, torque: f32) -> Result<(), ClientError> {
+        let Some(wheel) = &mut self.wheel else {
+            return Err(ClientError::ValidationFailure {
+                performing: "spinning the wheel".to_string(),
+                part: "wheel".to_string(),
+                reason: "This satellite has no reaction wheel".to_string(),
+            });
+        };
+
+        if torque.abs() > wheel.spec.max_torque {
+            return Err(ClientError::ValidationFailure {
+                performing: "spinning the wheel".to_string(),
+                part: "torque".to_string(),
+                reason: format!("torque should be within ±{}", wheel.spec.max_torque),
+            });
+        }
+
+        wheel.set_command(torque);
+
+        Ok(())
+    }
+
+    fn wheel_speed(&self) -> Result<f32, ClientError> {
+        self.wheel
+            .as_ref()
+            .map(ReactionWheel::speed)
+            .ok_or_else(|| ClientError::ValidationFailure {
+                performing: "reading the wheel speed".to_string(),
+                part: "wheel".to_string(),
+                reason: "This satellite has no reaction wheel".to_string(),
+            })
+    }
+
+    fn damage(&self, part: &str) -> Result<f32, ClientError> {
+        self.health
+            .iter()
+            .find(|candidate| candidate.name == part)
+            .map(PartHealth::damage)
+            .ok_or_else(|| ClientError::ValidationFailure {
+                performing: "reading damage".to_string(),
+                part: "part".to_string(),
+                reason: format!("Unknown part ({part})"),
+            })
+    }
+
+    fn range(&self, sensor: &str) -> Result<Option<f32>, ClientError> {
+        self.sensors
+            .iter()
+            .find(|candidate| candidate.name == sensor)
+            .map(|sensor| sensor.reading)
+            .ok_or_else(|| ClientError::ValidationFailure {
+                performing: "ranging".to_string(),
+                part: "sensor".to_string(),
+                reason: format!("Unknown sensor ({sensor})"),
+            })
+    }
+
+    fn nearest(&self, kind: &str) -> Result<Option<f32>, ClientError> {
+        self.proximity
+            .get(kind)
+            .copied()
+            .ok_or_else(|| ClientError::ValidationFailure {
+                performing: "sensing proximity".to_string(),
+                part: "kind".to_string(),
+                reason: format!("Unknown kind ({kind})"),
+            })
+    }
+
+    fn report_proximity(&mut self, proximity: HashMap<String, Option<f32>>) {
+        self.proximity = proximity;
+    }
+
+    fn deploy(&mut self, payload: &str, firmware: Option<String>) -> Result<(), ClientError> {
+        let Some(design) = self.design.payload(payload) else {
+            return Err(ClientError::ValidationFailure {
+                performing: "deploying".to_string(),
+                part: "payload".to_string(),
+                reason: format!("Unknown payload ({payload})"),
+            });
+        };
+
+        let remaining = self.payloads.entry(design.name.clone()).or_default();
+        if *remaining == 0 {
+            return Err(ClientError::ValidationFailure {
+                performing: "deploying".to_string(),
+                part: "payload".to_string(),
+                reason: format!("No {payload} left in the bay"),
+            });
+        }
+        *remaining -= 1;
+
+        let (x, y) = design.local_impulse();
+        self.recoil.push((design.at, (-x, -y)));
+        self.deployments.push(Deployment {
+            payload: design.name.clone(),
+            firmware,
+        });
+
+        Ok(())
+    }
+
+    fn alignment(&self, port: &str) -> Result<Option<Alignment>, ClientError> {
+        let port = self.docking_port("reading alignment", port)?;
+
+        Ok(self.alignment.get(&port.name).copied().flatten())
+    }
+
+    fn docked(&self, port: &str) -> Result<bool, ClientError> {
+        let port = self.docking_port("reading docking status", port)?;
+
+        Ok(self.docks.iter().any(|dock| dock.port == port.name))
+    }
+
+    fn undock(&mut self, port: &str) -> Result<(), ClientError> {
+        let port = self.docking_port("undocking", port)?.clone();
+
+        if !self.docks.iter().any(|dock| dock.port == port.name) {
+            return Err(ClientError::ValidationFailure {
+                performing: "undocking".to_string(),
+                part: "port".to_string(),
+                reason: format!("Port ({}) is not docked", port.name),
+            });
+        }
+
+        if !self.undocking.contains(&port.name) {
+            let (x, y) = port.unit_direction();
+            let impulse = self.design.mass * UNDOCK_SPEED;
+
+            self.recoil.push((port.at, (-x * impulse, -y * impulse)));
+            self.undocking.push(port.name);
+        }
+
+        Ok(())
+    }
+
+    fn radio_send(&mut self, channel: &str, message: String) -> Result<bool, ClientError> {
+        let Some(radio) = &mut self.radio else {
+            return Err(ClientError::ValidationFailure {
+                performing: "sending over the radio".to_string(),
+                part: "radio".to_string(),
+                reason: "This satellite has no radio".to_string(),
+            });
+        };
+
+        Ok(radio.send(RadioMessage {
+            channel: channel.to_string(),
+            message,
+        }))
+    }
+
+    fn radio_receive(&mut self) -> Result<Option<(String, String)>, ClientError> {
+        let Some(radio) = &mut self.radio else {
+            return Err(ClientError::ValidationFailure {
+                performing: "receiving over the radio".to_string(),
+                part: "radio".to_string(),
+                reason: "This satellite has no radio".to_string(),
+            });
+        };
+
+        Ok(radio
+            .receive()
+            .map(|RadioMessage { channel, message }| (channel, message)))
+    }
+
+    fn telemetry(&self) -> Telemetry {
+        Telemetry {
+            position: self.transform.location,
+            velocity: self.motion.linear,
+            angle: self.transform.angle,
+            angular_velocity: self.motion.angular,
         }
     }
 }
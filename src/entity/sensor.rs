@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+use crate::theory::geometry::{rotate_vec2, Transform};
+use crate::theory::physics::{PhysicalWorld, Physics};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RangeSensor {
+    pub name: String,
+    pub mount: (f32, f32),
+    pub direction: (f32, f32),
+    pub max_range: f32,
+    pub reading: Option<f32>,
+}
+
+impl RangeSensor {
+    /// `direction` is normalized, and must not be zero.
+    pub fn new(name: &str, mount: (f32, f32), direction: (f32, f32), max_range: f32) -> Self {
+        let length = direction.0.hypot(direction.1);
+        assert!(length > 0.0, "Range sensor ({name}) has no direction");
+
+        Self {
+            name: name.to_string(),
+            mount,
+            direction: (direction.0 / length, direction.1 / length),
+            max_range,
+            reading: None,
+        }
+    }
+
+    pub fn measure(&mut self, world: &PhysicalWorld, transform: &Transform, owner: &Physics) {
+        let mount = rotate_vec2(transform.angle, self.mount);
+        let origin = (
+            transform.location.0 + mount.0,
+            transform.location.1 + mount.1,
+        );
+        let direction = rotate_vec2(transform.angle, self.direction);
+
+        self.reading = world.cast_ray(origin, direction, self.max_range, owner);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::*;
+    use crate::theory::physics::{BodyKind, ColliderShape, Motion, RigidBodyProperty};
+
+    fn ball(world: &mut PhysicalWorld, location: (f32, f32), tag: u128) -> Physics {
+        world.register(
+            RigidBodyProperty {
+                kind: BodyKind::Fixed,
+                mass: 1.0,
+                shape: ColliderShape::Ball { radius: 1.0 },
+                initial_transform: Transform::new(location, 0.0),
+                initial_motion: Motion::default(),
+            },
+            tag,
+        )
+    }
+
+    #[test]
+    fn sensor_should_read_the_distance_to_the_body_ahead() {
+        let mut world = PhysicalWorld::new();
+        let owner = ball(&mut world, (0.0, 0.0), 0);
+        ball(&mut world, (0.0, -5.0), 1);
+        world.tick();
+
+        let mut sensor = RangeSensor::new("front", (0.0, -1.0), (0.0, -2.0), 10.0);
+
+        sensor.measure(&world, &Transform::default(), &owner);
+        assert!((sensor.reading.unwrap() - 3.0).abs() < 1e-3);
+
+        sensor.measure(&world, &Transform::new((0.0, 0.0), PI), &owner);
+        assert_eq!(sensor.reading, None);
+    }
+}
@@ -0,0 +1,166 @@
+use ggez::{
+    glam::Vec2,
+    graphics::{self, Color},
+};
+use serde::{Deserialize, Serialize};
+
+use super::docking::DockingPort;
+use super::{Capabilities, Dockable, DrawInstruction, Drawable, Entity, EntityKind, RigidBody};
+use crate::system::state::GameState;
+use crate::theory::geometry::Transform;
+use crate::theory::physics::{
+    BodyKind, ColliderShape, Motion, Physics, PhysicsController, RigidBodyProperty,
+};
+use crate::theory::units::to_pixels;
+
+const MASS: f32 = 20000.0;
+
+/// Modules of the station as (center, size), in meters relative to the station's center
+const MODULES: [((f32, f32), (f32, f32)); 3] = [
+    ((0.0, 0.0), (3.0, 3.0)),
+    ((-4.5, 0.0), (6.0, 1.2)),
+    ((4.5, 0.0), (6.0, 1.2)),
+];
+
+/// The docking port sits on the top face of the core module
+const PORT: (f32, f32) = (0.0, -1.5);
+const PORT_SIZE: (f32, f32) = (0.8, 0.2);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Station {
+    pub physics: Option<Physics>,
+    pub transform: Transform,
+    pub motion: Motion,
+    pub ports: Vec<DockingPort>,
+}
+
+impl Station {
+    pub fn new(transform: Transform) -> Self {
+        Self {
+            physics: None,
+            transform,
+            motion: Motion::default(),
+            ports: vec![DockingPort::new("main", PORT, (0.0, -1.0))],
+        }
+    }
+
+    fn extent() -> f32 {
+        MODULES
+            .iter()
+            .map(|((x, y), (width, height))| x.abs().max(y.abs()) + width.max(*height) / 2.0)
+            .fold(0.0, f32::max)
+    }
+}
+
+impl EntityKind for Station {
+    const NAME: &'static str = "station";
+    const CAPABILITIES: Capabilities = Capabilities::DRAWABLE
+        .union(Capabilities::RIGID_BODY)
+        .union(Capabilities::DOCKABLE);
+}
+
+impl Entity for Station {
+    fn as_drawable(&self) -> Option<&dyn Drawable> {
+        Some(self)
+    }
+
+    fn as_rigidbody(&mut self) -> Option<&mut dyn RigidBody> {
+        Some(self)
+    }
+
+    fn as_dockable(&self) -> Option<&dyn Dockable> {
+        Some(self)
+    }
+}
+
+impl Drawable for Station {
+    fn draw(
+        &self,
+        canvas: &mut graphics::Canvas,
+        _state: &GameState,
+    ) -> ggez::GameResult<DrawInstruction> {
+        let extent = to_pixels(Self::extent());
+
+        let mut draw_box = |at: (f32, f32), size: (f32, f32), color: Color| {
+            canvas.draw(
+                &graphics::Quad,
+                graphics::DrawParam::new()
+                    .dest(Vec2::new(
+                        extent + to_pixels(at.0),
+                        extent + to_pixels(at.1),
+                    ))
+                    .offset(Vec2::new(0.5, 0.5))
+                    .scale(Vec2::new(to_pixels(size.0), to_pixels(size.1)))
+                    .color(color),
+            );
+        };
+
+        draw_box(MODULES[1].0, MODULES[1].1, Color::from_rgb(40, 70, 140));
+        draw_box(MODULES[2].0, MODULES[2].1, Color::from_rgb(40, 70, 140));
+        draw_box(MODULES[0].0, MODULES[0].1, Color::from_rgb(200, 200, 210));
+        for port in &self.ports {
+            draw_box(port.at, PORT_SIZE, Color::YELLOW);
+        }
+
+        Ok(DrawInstruction {
+            position: self.transform.location.into(),
+            angle: self.transform.angle,
+            size: Vec2::new(extent * 2.0, extent * 2.0),
+            ..Default::default()
+        })
+    }
+}
+
+impl RigidBody for Station {
+    fn get_property(&self) -> RigidBodyProperty {
+        RigidBodyProperty {
+            kind: BodyKind::Dynamic,
+            mass: MASS,
+            shape: ColliderShape::Compound(
+                MODULES
+                    .iter()
+                    .map(|(at, (width, height))| {
+                        (
+                            Transform::new(*at, 0.0),
+                            ColliderShape::Cuboid {
+                                width: *width,
+                                height: *height,
+                            },
+                        )
+                    })
+                    .collect(),
+            ),
+            initial_transform: self.transform.clone(),
+            initial_motion: self.motion.clone(),
+        }
+    }
+
+    fn register_physics(&mut self, physics: Physics) {
+        self.physics = Some(physics);
+    }
+
+    fn get_mut_physics(&mut self) -> &mut Physics {
+        self.physics.as_mut().unwrap()
+    }
+
+    fn update_physics(&mut self, _controller: &mut PhysicsController) {}
+
+    fn report_transform(&mut self, transform: Transform, motion: Motion) {
+        self.transform = transform;
+        self.motion = motion;
+    }
+}
+
+impl Dockable for Station {
+    fn ports(&self) -> &[DockingPort] {
+        &self.ports
+    }
+
+    fn body(&self) -> Option<Physics> {
+        self.physics
+    }
+
+    fn frame(&self) -> (&Transform, &Motion) {
+        (&self.transform, &self.motion)
+    }
+}
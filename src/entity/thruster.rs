@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+
+const OUTPUT_CUTOFF: f32 = 1e-4;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ThrusterSpec {
+    /// Thrust at full throttle, in newtons
+    pub max_thrust: f32,
+    /// Time constants of the first-order lag when throttling up and down, in seconds
+    #[serde(default)]
+    pub spool_up: f32,
+    #[serde(default)]
+    pub spool_down: f32,
+    /// The smallest impulse one firing can deliver, in newton-seconds
+    #[serde(default)]
+    pub min_impulse_bit: f32,
+    /// On/off-only valve; commands are rounded to either 0 or 1
+    #[serde(default)]
+    pub bang_bang: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Thruster {
+    pub spec: ThrusterSpec,
+    command: f32,
+    held_command: f32,
+    output: f32,
+    valve_open: bool,
+    firing_impulse: f32,
+    health: f32,
+}
+
+impl ThrusterSpec {
+    pub fn ideal(max_thrust: f32) -> Self {
+        Self {
+            max_thrust,
+            spool_up: 0.0,
+            spool_down: 0.0,
+            min_impulse_bit: 0.0,
+            bang_bang: false,
+        }
+    }
+}
+
+impl Thruster {
+    pub fn new(spec: ThrusterSpec) -> Self {
+        Self {
+            spec,
+            command: 0.0,
+            held_command: 0.0,
+            output: 0.0,
+            valve_open: false,
+            firing_impulse: 0.0,
+            health: 1.0,
+        }
+    }
+
+    pub fn set_command(&mut self, power: f32) {
+        self.command = match self.spec.bang_bang {
+            true if power >= 0.5 => 1.0,
+            true => 0.0,
+            false => power,
+        };
+    }
+
+    /// Scales the thrust down to `health`; a thruster with no health fires no more.
+    pub fn set_health(&mut self, health: f32) {
+        self.health = health.clamp(0.0, 1.0);
+    }
+
+    pub fn thrust(&self) -> f32 {
+        self.output * self.spec.max_thrust * self.health
+    }
+
+    /// Advances the thruster by `dt` seconds and returns the thrust to apply, in newtons.
+    pub fn step(&mut self, dt: f32) -> f32 {
+        let holding = self.valve_open && self.firing_impulse < self.spec.min_impulse_bit;
+        let target = if self.command > 0.0 {
+            self.held_command = self.command;
+            self.command
+        } else if holding {
+            self.held_command
+        } else {
+            0.0
+        };
+
+        self.valve_open = target > 0.0;
+        if !self.valve_open {
+            self.firing_impulse = 0.0;
+        }
+
+        let time_constant = if target > self.output {
+            self.spec.spool_up
+        } else {
+            self.spec.spool_down
+        };
+        let response = if time_constant > 0.0 {
+            1.0 - (-dt / time_constant).exp()
+        } else {
+            1.0
+        };
+
+        self.output += (target - self.output) * response;
+        if self.output < OUTPUT_CUTOFF {
+            self.output = 0.0;
+        }
+
+        let thrust = self.thrust();
+        if self.valve_open {
+            self.firing_impulse += thrust * dt;
+        }
+
+        thrust
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    #[test]
+    fn ideal_thruster_should_follow_command_instantly() {
+        let mut thruster = Thruster::new(ThrusterSpec::ideal(100.0));
+
+        thruster.set_command(0.5);
+        assert_eq!(thruster.step(DT), 50.0);
+
+        thruster.set_command(0.0);
+        assert_eq!(thruster.step(DT), 0.0);
+    }
+
+    #[test]
+    fn thruster_should_spool_up_with_time_constant() {
+        let mut thruster = Thruster::new(ThrusterSpec {
+            spool_up: 0.5,
+            ..ThrusterSpec::ideal(100.0)
+        });
+
+        thruster.set_command(1.0);
+        let thrust = (0..30).map(|_| thruster.step(DT)).last().unwrap();
+
+        // One time constant reaches 1 - 1/e of the full thrust
+        assert!((thrust - 63.2).abs() < 0.5, "thrust was {thrust}");
+    }
+
+    #[test]
+    fn thruster_should_deliver_minimum_impulse_bit() {
+        let mut thruster = Thruster::new(ThrusterSpec {
+            min_impulse_bit: 5.0,
+            ..ThrusterSpec::ideal(100.0)
+        });
+
+        thruster.set_command(1.0);
+        let mut impulse = thruster.step(DT) * DT;
+
+        thruster.set_command(0.0);
+        impulse += (0..10).map(|_| thruster.step(DT) * DT).sum::<f32>();
+
+        assert!(impulse >= 5.0, "impulse was {impulse}");
+        assert_eq!(thruster.step(DT), 0.0);
+    }
+
+    #[test]
+    fn bang_bang_thruster_should_round_commands() {
+        let mut thruster = Thruster::new(ThrusterSpec {
+            bang_bang: true,
+            ..ThrusterSpec::ideal(100.0)
+        });
+
+        thruster.set_command(0.4);
+        assert_eq!(thruster.step(DT), 0.0);
+
+        thruster.set_command(0.6);
+        assert_eq!(thruster.step(DT), 100.0);
+    }
+}
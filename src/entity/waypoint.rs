@@ -0,0 +1,84 @@
+use ggez::{
+    glam::Vec2,
+    graphics::{self, Color},
+};
+use serde::{Deserialize, Serialize};
+
+use super::{Capabilities, DrawInstruction, Drawable, Entity, EntityKind};
+use crate::system::state::GameState;
+use crate::theory::units::to_pixels;
+use crate::world::WorldKey;
+
+/// A marker with no physical body; ships pass right through it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Waypoint {
+    pub name: String,
+    pub location: (f32, f32),
+    pub radius: f32,
+    /// Satellites that have entered the waypoint, in the order they arrived
+    pub reached_by: Vec<WorldKey>,
+}
+
+impl Waypoint {
+    pub fn new(name: &str, location: (f32, f32), radius: f32) -> Self {
+        Self {
+            name: name.to_string(),
+            location,
+            radius,
+            reached_by: Vec::new(),
+        }
+    }
+}
+
+impl EntityKind for Waypoint {
+    const NAME: &'static str = "waypoint";
+    const CAPABILITIES: Capabilities = Capabilities::DRAWABLE;
+}
+
+impl Entity for Waypoint {
+    fn as_drawable(&self) -> Option<&dyn Drawable> {
+        Some(self)
+    }
+}
+
+impl Drawable for Waypoint {
+    fn draw(
+        &self,
+        canvas: &mut graphics::Canvas,
+        state: &GameState,
+    ) -> ggez::GameResult<DrawInstruction> {
+        let radius = to_pixels(self.radius);
+        let center = Vec2::new(radius, radius);
+        let (r, g, b) = match self.reached_by.is_empty() {
+            true => (80, 220, 120),
+            false => (240, 200, 80),
+        };
+
+        canvas.draw(
+            &state.circle,
+            graphics::DrawParam::new()
+                .dest(center)
+                .scale(Vec2::new(radius, radius))
+                .color(Color::from_rgba(r, g, b, 60)),
+        );
+        canvas.draw(
+            &state.circle,
+            graphics::DrawParam::new()
+                .dest(center)
+                .scale(Vec2::new(4.0, 4.0))
+                .color(Color::from_rgb(r, g, b)),
+        );
+        canvas.draw(
+            &graphics::Text::new(self.name.as_str()),
+            graphics::DrawParam::new()
+                .dest(center + Vec2::new(8.0, 8.0))
+                .color(Color::WHITE),
+        );
+
+        Ok(DrawInstruction {
+            position: self.location.into(),
+            size: Vec2::new(radius * 2.0, radius * 2.0),
+            ..Default::default()
+        })
+    }
+}
@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WheelSpec {
+    /// Largest torque the motor can exert, in newton-meters
+    pub max_torque: f32,
+    /// Momentum the wheel can store before saturating, in newton-meter-seconds
+    pub max_momentum: f32,
+    /// Moment of inertia of the rotor, in kilogram-square-meters
+    pub inertia: f32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReactionWheel {
+    pub spec: WheelSpec,
+    command: f32,
+    momentum: f32,
+}
+
+impl ReactionWheel {
+    pub fn new(spec: WheelSpec) -> Self {
+        Self {
+            spec,
+            command: 0.0,
+            momentum: 0.0,
+        }
+    }
+
+    pub fn set_command(&mut self, torque: f32) {
+        self.command = torque;
+    }
+
+    pub fn momentum(&self) -> f32 {
+        self.momentum
+    }
+
+    /// Angular velocity of the rotor, in radians per second.
+    pub fn speed(&self) -> f32 {
+        self.momentum / self.spec.inertia
+    }
+
+    pub fn is_saturated(&self) -> bool {
+        self.momentum.abs() >= self.spec.max_momentum
+    }
+
+    /// Advances the wheel by `dt` seconds and returns the torque it exerts on the body.
+    /// The rotor takes the opposite momentum, and stops accelerating once saturated.
+    pub fn step(&mut self, dt: f32) -> f32 {
+        let limit = self.spec.max_momentum;
+        let momentum = (self.momentum - self.command * dt).clamp(-limit, limit);
+
+        let torque = (self.momentum - momentum) / dt;
+        self.momentum = momentum;
+
+        torque
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    fn wheel() -> ReactionWheel {
+        ReactionWheel::new(WheelSpec {
+            max_torque: 2.0,
+            max_momentum: 1.0,
+            inertia: 0.01,
+        })
+    }
+
+    #[test]
+    fn wheel_should_store_opposite_momentum() {
+        let mut wheel = wheel();
+
+        wheel.set_command(1.5);
+        let torque = wheel.step(DT);
+
+        assert!((torque - 1.5).abs() < 1e-4);
+        assert!((wheel.momentum() + 1.5 * DT).abs() < 1e-6);
+        assert!((wheel.speed() + 150.0 * DT).abs() < 1e-4);
+    }
+
+    #[test]
+    fn wheel_should_stop_exerting_torque_when_saturated() {
+        let mut wheel = wheel();
+
+        wheel.set_command(2.0);
+        let impulse: f32 = (0..120).map(|_| wheel.step(DT) * DT).sum();
+
+        assert!(wheel.is_saturated());
+        assert!((impulse - 1.0).abs() < 1e-4);
+        assert_eq!(wheel.step(DT), 0.0);
+
+        wheel.set_command(-2.0);
+        assert!(wheel.step(DT) < 0.0);
+        assert!(!wheel.is_saturated());
+    }
+}
@@ -2,20 +2,44 @@ use rfd::AsyncFileDialog;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
-#[derive(Default)]
 pub struct FileDialog {
     selected_file: Arc<Mutex<Option<PathBuf>>>,
+    filter: (&'static str, &'static [&'static str]),
+}
+
+impl Default for FileDialog {
+    fn default() -> Self {
+        Self::new("Lua program", &["lua"])
+    }
 }
 
 impl FileDialog {
+    pub fn new(name: &'static str, extensions: &'static [&'static str]) -> Self {
+        Self {
+            selected_file: Arc::default(),
+            filter: (name, extensions),
+        }
+    }
+
     pub fn show(&mut self) {
+        self.spawn(false);
+    }
+
+    /// Asks for a file to write to, which may not exist yet.
+    pub fn show_save(&mut self) {
+        self.spawn(true);
+    }
+
+    fn spawn(&mut self, save: bool) {
         let selected_file = self.selected_file.clone();
+        let (name, extensions) = self.filter;
 
         tokio::spawn(async move {
-            let selected = AsyncFileDialog::default()
-                .add_filter("Lua program", &["lua"])
-                .pick_file()
-                .await;
+            let dialog = AsyncFileDialog::default().add_filter(name, extensions);
+            let selected = match save {
+                true => dialog.save_file().await,
+                false => dialog.pick_file().await,
+            };
 
             let mut selected_file = selected_file.lock().unwrap();
             *selected_file = selected.as_ref().map(|file| file.path().to_path_buf());
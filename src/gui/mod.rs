@@ -5,7 +5,10 @@ use ggez::{Context, GameResult};
 
 use crate::entity::{DrawInstruction, DrawOrigin};
 use crate::gui::file_selector::FileDialog;
-use crate::system::state::GameState;
+use crate::system::simulation::KeyboardRouting;
+use crate::system::state::{GameState, PortStatus};
+use crate::system::viewport::Viewport;
+use crate::world::WorldKey;
 use ggez::glam::Vec2;
 use ggez::graphics::{Canvas, DrawParam};
 
@@ -14,6 +17,11 @@ mod file_selector;
 pub struct GUIEntity {
     gui: Gui,
     file_dialog: FileDialog,
+    scene_dialog: FileDialog,
+    save_dialog: FileDialog,
+    session_dialog: FileDialog,
+    save_session_dialog: FileDialog,
+    rewind_index: usize,
 }
 
 impl Debug for GUIEntity {
@@ -27,6 +35,11 @@ impl GUIEntity {
         GUIEntity {
             gui: Gui::new(ctx),
             file_dialog: FileDialog::default(),
+            scene_dialog: FileDialog::new("Scene", &["ron"]),
+            save_dialog: FileDialog::new("Scene", &["ron"]),
+            session_dialog: FileDialog::new("Session", &["ron"]),
+            save_session_dialog: FileDialog::new("Session", &["ron"]),
+            rewind_index: 0,
         }
     }
 }
@@ -36,32 +49,212 @@ impl GUIEntity {
         let gui_ctx = self.gui.ctx();
 
         egui::Window::new("Load program").show(&gui_ctx, |ui| {
+            let fleet = &mut state.fleet;
+            let name = |key: &WorldKey| {
+                let index = fleet
+                    .satellites
+                    .iter()
+                    .position(|candidate| candidate == key);
+                format!("Satellite {}", index.map_or(0, |index| index + 1))
+            };
+
+            egui::ComboBox::from_label("Target")
+                .selected_text(fleet.selected.as_ref().map_or("None".to_string(), name))
+                .show_ui(ui, |ui| {
+                    fleet.satellites.iter().for_each(|key| {
+                        ui.selectable_value(&mut fleet.selected, Some(*key), name(key));
+                    });
+                });
+
             ui.label("Path of the program");
             if ui.button("Open the program").clicked() {
                 self.file_dialog.show();
             }
+
+            ui.label("Keyboard goes to");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut fleet.routing, KeyboardRouting::Selected, "Target");
+                ui.radio_value(&mut fleet.routing, KeyboardRouting::All, "All");
+                ui.radio_value(&mut fleet.routing, KeyboardRouting::Nobody, "Nobody");
+            });
+        });
+
+        egui::Window::new("Scene").show(&gui_ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Open scene").clicked() {
+                    self.scene_dialog.show();
+                }
+                if ui.button("Save scene").clicked() {
+                    self.save_dialog.show_save();
+                }
+            });
+
+            if let Some(error) = &state.scene.error {
+                ui.label(error);
+            }
+        });
+
+        egui::Window::new("Session").show(&gui_ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Load session").clicked() {
+                    self.session_dialog.show();
+                }
+                if ui.button("Save session").clicked() {
+                    self.save_session_dialog.show_save();
+                }
+            });
+            ui.label("Quick-save (F9), quick-load (F10)");
+
+            if let Some(error) = &state.session.error {
+                ui.label(error);
+            }
+        });
+
+        egui::Window::new("Time").show(&gui_ctx, |ui| {
+            let clock = &mut state.clock;
+
+            ui.horizontal(|ui| {
+                let label = if clock.is_paused() {
+                    "Resume (F5)"
+                } else {
+                    "Pause (F5)"
+                };
+                if ui.button(label).clicked() {
+                    clock.toggle_pause();
+                }
+                if ui.button("Step (F6)").clicked() {
+                    clock.request_step();
+                }
+            });
+
+            ui.horizontal(|ui| {
+                if ui.button("Slower (F7)").clicked() {
+                    clock.slower();
+                }
+                ui.label(format!("{}x", clock.scale()));
+                if ui.button("Faster (F8)").clicked() {
+                    clock.faster();
+                }
+            });
+        });
+
+        egui::Window::new("Rewind").show(&gui_ctx, |ui| {
+            let ticks = &state.rewind.available_ticks;
+            if ticks.is_empty() {
+                ui.label("No snapshot has been taken yet");
+                return;
+            }
+
+            self.rewind_index = self.rewind_index.min(ticks.len() - 1);
+            ui.add(
+                egui::Slider::new(&mut self.rewind_index, 0..=ticks.len() - 1).show_value(false),
+            );
+            ui.label(format!("Tick: {}", ticks[self.rewind_index]));
+
+            let tick = ticks[self.rewind_index];
+            ui.checkbox(&mut state.rewind.keep_program, "Keep the current program");
+            if ui.button("Rewind").clicked() {
+                state.rewind.requested = Some(tick);
+            }
+        });
+
+        egui::Window::new("Damage").show(&gui_ctx, |ui| {
+            state.health.iter().for_each(|(part, health)| {
+                ui.horizontal(|ui| {
+                    ui.label(part);
+                    ui.add(egui::ProgressBar::new(*health).text(format!("{:.0}%", health * 100.0)));
+                });
+            });
+        });
+
+        egui::Window::new("Camera").show(&gui_ctx, |ui| {
+            let camera = &mut state.camera;
+
+            ui.checkbox(&mut camera.follow, "Follow the target");
+            ui.horizontal(|ui| {
+                ui.label(format!("Zoom: {:.2}x", camera.zoom()));
+                if ui.button("Reset").clicked() {
+                    camera.reset();
+                }
+            });
+            ui.label("Drag with the right button to pan, scroll to zoom");
+        });
+
+        egui::Window::new("Docking").show(&gui_ctx, |ui| {
+            if state.docking.is_empty() {
+                ui.label("The target has no docking port");
+            }
+
+            state.docking.iter().for_each(|(port, status)| {
+                ui.horizontal(|ui| {
+                    ui.label(port);
+                    match status {
+                        PortStatus::Docked => ui.label("Docked"),
+                        PortStatus::Aligning(alignment) => ui.label(format!(
+                            "{:.2} m, {:.1}° off, closing at {:.2} m/s",
+                            alignment.distance,
+                            alignment.misalignment.to_degrees(),
+                            alignment.closing_speed
+                        )),
+                        PortStatus::Free => ui.label("No port in range"),
+                    };
+                });
+            });
         });
         self.gui.update(ctx);
 
-        if let Some(program) = self.file_dialog.read_selected() {
-            state.load_lua_program(&program);
+        if let (Some(path), Some(program)) = (
+            self.file_dialog.get_selected(),
+            self.file_dialog.read_selected(),
+        ) {
+            state.load_lua_program(&path, &program);
             self.file_dialog.forget_selected();
         }
 
+        if let Some(path) = self.scene_dialog.get_selected() {
+            state.scene.load = Some(path);
+            self.scene_dialog.forget_selected();
+        }
+
+        if let Some(path) = self.save_dialog.get_selected() {
+            state.scene.save = Some(path);
+            self.save_dialog.forget_selected();
+        }
+
+        if let Some(path) = self.session_dialog.get_selected() {
+            state.session.load = Some(path);
+            self.session_dialog.forget_selected();
+        }
+
+        if let Some(path) = self.save_session_dialog.get_selected() {
+            state.session.save = Some(path);
+            self.save_session_dialog.forget_selected();
+        }
+
         Ok(())
     }
 
-    pub fn draw(&self, canvas: &mut Canvas, _state: &GameState) -> GameResult<DrawInstruction> {
+    pub fn draw(&self, canvas: &mut Canvas, state: &GameState) -> GameResult<DrawInstruction> {
         canvas.draw(&self.gui, DrawParam::default().dest(Vec2::ZERO));
 
         Ok(DrawInstruction {
-            size: (1920.0, 1080.0).into(),
+            size: state.viewport.size,
             draw_origin: DrawOrigin::ScreenAbsolute,
             position: Vec2::ZERO,
             angle: 0.0,
         })
     }
 
+    pub fn resize(&mut self, viewport: &Viewport) {
+        self.gui
+            .input
+            .set_scale_factor(viewport.scale_factor, viewport.size.into());
+    }
+
+    pub fn is_pointer_over(&mut self) -> bool {
+        self.gui.ctx().is_pointer_over_area()
+    }
+
     pub fn on_text_input(&mut self, character: char) {
         self.gui.input.text_input_event(character);
     }
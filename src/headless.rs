@@ -0,0 +1,247 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use ggez::winit::event::VirtualKeyCode;
+
+use crate::lang::exec::ExecutionError;
+use crate::lang::{ClientError, ModKey, ProgramEnvironment};
+use crate::system::lang_env::map_char_to_keycode;
+use crate::system::scene::{Scene, SceneError, DEFAULT_SCENE};
+use crate::system::simulation::Simulation;
+use crate::world::WorldKey;
+
+#[derive(thiserror::Error, Debug)]
+pub enum HeadlessError {
+    #[error("Invalid arguments: {0}")]
+    InvalidArguments(String),
+
+    #[error("Invalid input script at line {line}: {reason}")]
+    InvalidInputScript { line: usize, reason: String },
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Could not load the scene: {0}")]
+    Scene(#[from] SceneError),
+
+    #[error("Could not load the firmware: {0}")]
+    Firmware(#[from] ExecutionError),
+}
+
+#[derive(Debug)]
+pub struct HeadlessOptions {
+    /// Loaded onto the selected satellite, over whatever the scene attached to it
+    pub firmware: Option<PathBuf>,
+    pub ticks: u64,
+    pub out: PathBuf,
+    pub inputs: Option<PathBuf>,
+    /// Replaces every design the scene refers to
+    pub design: Option<PathBuf>,
+    pub scene: Option<PathBuf>,
+}
+
+impl HeadlessOptions {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, HeadlessError> {
+        let mut firmware = None;
+        let mut ticks = None;
+        let mut out = None;
+        let mut inputs = None;
+        let mut design = None;
+        let mut scene = None;
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| HeadlessError::InvalidArguments(format!("{arg} needs a value")))
+            };
+
+            match arg.as_str() {
+                "--firmware" => firmware = Some(PathBuf::from(value()?)),
+                "--ticks" => {
+                    ticks = Some(value()?.parse().map_err(|_| {
+                        HeadlessError::InvalidArguments("--ticks should be a number".to_string())
+                    })?)
+                }
+                "--out" => out = Some(PathBuf::from(value()?)),
+                "--inputs" => inputs = Some(PathBuf::from(value()?)),
+                "--design" => design = Some(PathBuf::from(value()?)),
+                "--scene" => scene = Some(PathBuf::from(value()?)),
+                _ => {
+                    return Err(HeadlessError::InvalidArguments(format!(
+                        "Unknown argument ({arg})"
+                    )))
+                }
+            }
+        }
+
+        let missing = |name: &str| HeadlessError::InvalidArguments(format!("{name} is required"));
+
+        Ok(Self {
+            firmware,
+            ticks: ticks.ok_or_else(|| missing("--ticks"))?,
+            out: out.ok_or_else(|| missing("--out"))?,
+            inputs,
+            design,
+            scene,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct ScriptedInput {
+    from: u64,
+    to: u64,
+    key: VirtualKeyCode,
+}
+
+/// Replays key presses from an input script, where each line is `<from>..<to> <key>`.
+#[derive(Debug, Default)]
+pub struct ScriptedEnvironment {
+    inputs: Vec<ScriptedInput>,
+    tick: u64,
+}
+
+impl ScriptedEnvironment {
+    pub fn parse(script: &str) -> Result<Self, HeadlessError> {
+        let inputs = script
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .map(|(line, content)| {
+                let invalid = |reason: &str| HeadlessError::InvalidInputScript {
+                    line,
+                    reason: reason.to_string(),
+                };
+
+                let (range, key) = content
+                    .split_once(char::is_whitespace)
+                    .ok_or_else(|| invalid("expected '<from>..<to> <key>'"))?;
+                let (from, to) = range
+                    .split_once("..")
+                    .ok_or_else(|| invalid("expected a tick range like '0..60'"))?;
+
+                Ok(ScriptedInput {
+                    from: from.parse().map_err(|_| invalid("invalid start tick"))?,
+                    to: to.parse().map_err(|_| invalid("invalid end tick"))?,
+                    key: map_char_to_keycode(key.trim()).ok_or_else(|| invalid("unknown key"))?,
+                })
+            })
+            .collect::<Result<Vec<_>, HeadlessError>>()?;
+
+        Ok(Self { inputs, tick: 0 })
+    }
+}
+
+impl ProgramEnvironment for ScriptedEnvironment {
+    fn is_pressed(&self, char: &str, _mods: Option<ModKey>) -> Result<bool, ClientError> {
+        let keycode = map_char_to_keycode(char).ok_or(ClientError::ValidationFailure {
+            performing: "Key press check".to_string(),
+            part: "char".to_string(),
+            reason: format!("No such key: {char}"),
+        })?;
+
+        Ok(self
+            .inputs
+            .iter()
+            .any(|input| input.key == keycode && (input.from..input.to).contains(&self.tick)))
+    }
+}
+
+pub fn run(options: &HeadlessOptions, resource_dir: &Path) -> Result<(), HeadlessError> {
+    let scene_path = match &options.scene {
+        Some(path) => path.clone(),
+        None => resource_dir.join(DEFAULT_SCENE),
+    };
+    let scene = Scene::parse(&fs::read_to_string(&scene_path)?)?;
+
+    let read_asset = |path: &str| match &options.design {
+        Some(design) if path.starts_with("designs/") => fs::read(design),
+        _ => fs::read(resource_dir.join(path)),
+    };
+    let base = scene_path.parent().unwrap_or(Path::new("."));
+    let mut simulation = scene.build(read_asset, base)?;
+
+    let Some(satellite_key) = simulation.selected else {
+        return Err(HeadlessError::InvalidArguments(
+            "The scene has no satellite".to_string(),
+        ));
+    };
+    if let Some(firmware) = &options.firmware {
+        simulation.load_firmware(&satellite_key, firmware, &fs::read_to_string(firmware)?)?;
+    }
+
+    let mut env = match &options.inputs {
+        Some(path) => ScriptedEnvironment::parse(&fs::read_to_string(path)?)?,
+        None => ScriptedEnvironment::default(),
+    };
+
+    let mut out = BufWriter::new(File::create(&options.out)?);
+    writeln!(out, "tick,x,y,angle,vx,vy")?;
+    write_trajectory(&mut out, &simulation, &satellite_key)?;
+
+    for _ in 0..options.ticks {
+        env.tick = simulation.tick;
+
+        for (key, err) in simulation.step(&env) {
+            eprintln!("[tick {}] [{key}] {err}", simulation.tick);
+        }
+
+        write_trajectory(&mut out, &simulation, &satellite_key)?;
+    }
+
+    out.flush()?;
+
+    Ok(())
+}
+
+fn write_trajectory(
+    out: &mut impl Write,
+    simulation: &Simulation,
+    key: &WorldKey,
+) -> std::io::Result<()> {
+    let Some(satellite) = simulation.satellite(key) else {
+        return Ok(());
+    };
+
+    let (transform, motion) = (&satellite.transform, &satellite.motion);
+    writeln!(
+        out,
+        "{},{},{},{},{},{}",
+        simulation.tick,
+        transform.location.0,
+        transform.location.1,
+        transform.angle,
+        motion.linear.0,
+        motion.linear.1
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripted_environment_should_press_keys_within_range() {
+        let mut env = ScriptedEnvironment::parse("# thrust forward\n0..2 w\n\n1..3 space").unwrap();
+
+        env.tick = 0;
+        assert!(env.is_pressed("w", None).unwrap());
+        assert!(!env.is_pressed(" ", None).unwrap());
+
+        env.tick = 2;
+        assert!(!env.is_pressed("W", None).unwrap());
+        assert!(env.is_pressed("space", None).unwrap());
+    }
+
+    #[test]
+    fn scripted_environment_should_report_the_broken_line() {
+        let result = ScriptedEnvironment::parse("0..2 w\n10 w");
+
+        assert!(matches!(
+            result,
+            Err(HeadlessError::InvalidInputScript { line: 2, .. })
+        ));
+    }
+}
@@ -1,8 +1,8 @@
 use std::sync::{Arc, Mutex};
 
-use rlua::{Result as LuaResult, Scope, Table};
+use rlua::{Error as LuaError, Result as LuaResult, Scope, Table};
 
-use super::{ClientError, ProgramClient, ProgramEnvironment};
+use super::{Alignment, ClientError, ProgramClient, ProgramEnvironment};
 
 use crate::lang::ModKey;
 
@@ -24,13 +24,142 @@ impl APIError {
     }
 }
 
-pub fn boost<T: ProgramClient>(client: &mut T, location: String, power: f32) -> APIResult<()> {
+pub fn boost<T: ProgramClient + ?Sized>(
+    client: &mut T,
+    location: String,
+    power: f32,
+) -> APIResult<()> {
     client
         .boost(&location, power)
         .map_err(|err| APIError::new("boost", err))
 }
 
-pub fn is_pressed<T: ProgramEnvironment>(env: &T, char: String, mods: Option<u8>) -> APIResult<bool> {
+pub fn thrust<T: ProgramClient + ?Sized>(client: &T, location: String) -> APIResult<f32> {
+    client
+        .thrust(&location)
+        .map_err(|err| APIError::new("thrust", err))
+}
+
+pub fn wheel<T: ProgramClient + ?Sized>(client: &mut T, torque: f32) -> APIResult<()> {
+    client
+        .wheel(torque)
+        .map_err(|err| APIError::new("wheel", err))
+}
+
+pub fn wheel_speed<T: ProgramClient + ?Sized>(client: &T) -> APIResult<f32> {
+    client
+        .wheel_speed()
+        .map_err(|err| APIError::new("wheel_speed", err))
+}
+
+pub fn damage<T: ProgramClient + ?Sized>(client: &T, part: String) -> APIResult<f32> {
+    client
+        .damage(&part)
+        .map_err(|err| APIError::new("damage", err))
+}
+
+pub fn range<T: ProgramClient + ?Sized>(client: &T, sensor: String) -> APIResult<Option<f32>> {
+    client
+        .range(&sensor)
+        .map_err(|err| APIError::new("range", err))
+}
+
+pub fn nearest<T: ProgramClient + ?Sized>(client: &T, kind: String) -> APIResult<Option<f32>> {
+    client
+        .nearest(&kind)
+        .map_err(|err| APIError::new("nearest", err))
+}
+
+pub fn deploy<T: ProgramClient + ?Sized>(
+    client: &mut T,
+    payload: String,
+    firmware: Option<String>,
+) -> APIResult<()> {
+    client
+        .deploy(&payload, firmware)
+        .map_err(|err| APIError::new("deploy", err))
+}
+
+/// Distance, misalignment and closing speed towards the nearest free port, or nils when
+/// there is none in range or the port is docked.
+pub fn alignment<T: ProgramClient + ?Sized>(
+    client: &T,
+    port: String,
+) -> APIResult<(Option<f32>, Option<f32>, Option<f32>)> {
+    let alignment = client
+        .alignment(&port)
+        .map_err(|err| APIError::new("alignment", err))?;
+
+    Ok((
+        alignment.map(|Alignment { distance, .. }| distance),
+        alignment.map(|Alignment { misalignment, .. }| misalignment),
+        alignment.map(|Alignment { closing_speed, .. }| closing_speed),
+    ))
+}
+
+pub fn docked<T: ProgramClient + ?Sized>(client: &T, port: String) -> APIResult<bool> {
+    client
+        .docked(&port)
+        .map_err(|err| APIError::new("docked", err))
+}
+
+pub fn undock<T: ProgramClient + ?Sized>(client: &mut T, port: String) -> APIResult<()> {
+    client
+        .undock(&port)
+        .map_err(|err| APIError::new("undock", err))
+}
+
+/// `api.radio`
+pub mod radio {
+    use super::{APIError, APIResult, ProgramClient};
+
+    /// Whether the message fit in the bandwidth left for this tick; it is dropped otherwise.
+    pub fn send<T: ProgramClient + ?Sized>(
+        client: &mut T,
+        channel: String,
+        message: String,
+    ) -> APIResult<bool> {
+        client
+            .radio_send(&channel, message)
+            .map_err(|err| APIError::new("radio.send", err))
+    }
+
+    /// The channel and the message of the oldest message received, or nils when there is
+    /// none.
+    pub fn receive<T: ProgramClient + ?Sized>(
+        client: &mut T,
+    ) -> APIResult<(Option<String>, Option<String>)> {
+        let received = client
+            .radio_receive()
+            .map_err(|err| APIError::new("radio.receive", err))?;
+
+        Ok(received.map_or((None, None), |(channel, message)| {
+            (Some(channel), Some(message))
+        }))
+    }
+}
+
+pub fn position<T: ProgramClient + ?Sized>(client: &T) -> APIResult<(f32, f32)> {
+    Ok(client.telemetry().position)
+}
+
+pub fn velocity<T: ProgramClient + ?Sized>(client: &T) -> APIResult<(f32, f32)> {
+    Ok(client.telemetry().velocity)
+}
+
+pub fn angle<T: ProgramClient + ?Sized>(client: &T) -> APIResult<f32> {
+    Ok(client.telemetry().angle)
+}
+
+pub fn angular_velocity<T: ProgramClient + ?Sized>(client: &T) -> APIResult<f32> {
+    Ok(client.telemetry().angular_velocity)
+}
+
+pub fn is_pressed<T: ProgramEnvironment>(
+    env: &T,
+    char: String,
+    mods: Option<u8>,
+) -> APIResult<bool> {
     env.is_pressed(&char, mods.and_then(ModKey::from_bits))
         .map_err(|err| APIError::new("is_pressed", err))
 }
@@ -42,64 +171,96 @@ pub fn register_api<'global, 'scope, T, E>(
     env: &'scope E,
 ) -> LuaResult<()>
 where
-    T: ProgramClient + Send,
+    T: ProgramClient + Send + ?Sized,
     E: ProgramEnvironment + Send,
     'global: 'scope,
 {
+    use radio::{receive, send};
+
     let client = Arc::new(Mutex::new(client));
     let env = Arc::new(Mutex::new(env));
 
     macro_rules! register {
-        ($name: ident(client, env, $( $arg: ident ),+)) => {
+        ($name: ident $args: tt) => {
+            register!(api_table; $name $args);
+        };
+        ($table: ident; $name: ident(client)) => {
+            let cloned_client = client.clone();
+            $table.set(
+                stringify!($name),
+                scope.create_function(move |_, ()| {
+                    $name(*cloned_client.lock().unwrap()).map_err(LuaError::external)
+                })?,
+            )?;
+        };
+        ($table: ident; $name: ident(client, env, $( $arg: ident ),+)) => {
             let cloned_client = client.clone();
             let cloned_env = env.clone();
-            api_table.set(
+            $table.set(
                 stringify!($name),
                 scope.create_function(move |_, ($( $arg ),+)| {
-                    Ok($name(
+                    $name(
                         *cloned_client.lock().unwrap(),
                         *cloned_env.lock().unwrap(),
                         $( $arg ),+
-                    ).unwrap())
+                    ).map_err(LuaError::external)
                 })?,
             )?;
         };
-        ($name: ident(client, $( $arg: ident ),+)) => {
+        ($table: ident; $name: ident(client, $( $arg: ident ),+)) => {
             let cloned_client = client.clone();
-            api_table.set(
+            $table.set(
                 stringify!($name),
                 scope.create_function(move |_, ($( $arg ),+)| {
-                    Ok($name(
+                    $name(
                         *cloned_client.lock().unwrap(),
                         $( $arg ),+
-                    ).unwrap())
+                    ).map_err(LuaError::external)
                 })?,
             )?;
         };
-        ($name: ident(env, $( $arg: ident ),+)) => {
+        ($table: ident; $name: ident(env, $( $arg: ident ),+)) => {
             let cloned_env = env.clone();
-            api_table.set(
+            $table.set(
                 stringify!($name),
                 scope.create_function(move |_, ($( $arg ),+)| {
-                    Ok($name(
+                    $name(
                         *cloned_env.lock().unwrap(),
                         $( $arg ),+
-                    ).unwrap())
+                    ).map_err(LuaError::external)
                 })?,
             )?;
         };
-        ($name: ident($( $arg: ident ),+)) => {
-            api_table.set(
+        ($table: ident; $name: ident($( $arg: ident ),+)) => {
+            $table.set(
                 stringify!($name),
                 scope.create_function(move |_, ($( $arg ),+)| {
-                    Ok($name($( $arg ),+).unwrap())
+                    $name($( $arg ),+).map_err(LuaError::external)
                 })?,
             )?;
         };
     }
 
     register!(boost(client, location, power));
+    register!(thrust(client, location));
+    register!(wheel(client, torque));
+    register!(wheel_speed(client));
+    register!(damage(client, part));
+    register!(range(client, sensor));
+    register!(nearest(client, kind));
+    register!(deploy(client, payload, firmware));
+    register!(alignment(client, port));
+    register!(docked(client, port));
+    register!(undock(client, port));
+    register!(position(client));
+    register!(velocity(client));
+    register!(angle(client));
+    register!(angular_velocity(client));
     register!(is_pressed(env, location, power));
 
+    let radio_table: Table = api_table.get("radio")?;
+    register!(radio_table; send(client, channel, message));
+    register!(radio_table; receive(client));
+
     Ok(())
 }
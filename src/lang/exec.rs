@@ -1,7 +1,9 @@
 use rlua::{prelude::*, StdLib, Table};
 use rlua::{Error, Function};
+use serde::{Deserialize, Serialize};
 
 use super::api::register_api;
+use super::snapshot::{capture_globals, restore_globals, LuaValueSnapshot};
 use super::{ProgramClient, ProgramEnvironment};
 
 #[derive(thiserror::Error, Debug, PartialEq, Eq)]
@@ -30,6 +32,13 @@ pub enum ExecutionError {
 
 pub struct LuaProgramExecutor {
     runtime: Lua,
+    program: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ProgramSnapshot {
+    pub program: Option<String>,
+    pub globals: Vec<(String, LuaValueSnapshot)>,
 }
 
 impl Default for LuaProgramExecutor {
@@ -42,9 +51,14 @@ impl LuaProgramExecutor {
     pub fn new() -> Self {
         Self {
             runtime: Lua::new_with(StdLib::BASE),
+            program: None,
         }
     }
 
+    pub fn program(&self) -> Option<&str> {
+        self.program.as_deref()
+    }
+
     pub fn load(&mut self, program: &str) -> Result<(), ExecutionError> {
         self.runtime.context(|ctx| {
             let global = ctx.globals();
@@ -56,12 +70,36 @@ impl LuaProgramExecutor {
             }
 
             Ok(())
-        })
+        })?;
+
+        self.program = Some(program.to_string());
+
+        Ok(())
+    }
+
+    pub fn snapshot(&self) -> ProgramSnapshot {
+        ProgramSnapshot {
+            program: self.program.clone(),
+            globals: self.runtime.context(|ctx| capture_globals(&ctx.globals())),
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: &ProgramSnapshot) -> Result<(), ExecutionError> {
+        self.runtime = Lua::new_with(StdLib::BASE);
+        self.program = None;
+
+        let Some(program) = &snapshot.program else {
+            return Ok(());
+        };
+
+        self.load(program)?;
+        self.runtime
+            .context(|ctx| restore_globals(ctx, &snapshot.globals).map_err(map_execute_result))
     }
 
     pub fn execute<C, E>(&mut self, client: &mut C, env: &E) -> Result<(), ExecutionError>
     where
-        C: ProgramClient + Send,
+        C: ProgramClient + Send + ?Sized,
         E: ProgramEnvironment + Send,
     {
         let reported = self.runtime.context(|ctx| {
@@ -74,7 +112,7 @@ impl LuaProgramExecutor {
             let main: Function = global.get("main").map_err(map_execute_result)?;
 
             let api_table: Table = ctx
-                .load("api = {{}}; return api")
+                .load("api = { radio = {} }; return api")
                 .eval()
                 .map_err(map_execute_result)?;
 
@@ -160,7 +198,7 @@ fn map_execute_result(error: LuaError) -> ExecutionError {
 mod tests {
     use std::collections::HashMap;
 
-    use crate::lang::{ClientError, ModKey};
+    use crate::lang::{ClientError, ModKey, Telemetry};
 
     use super::*;
 
@@ -193,11 +231,31 @@ mod tests {
             self.booster.insert(location.to_string(), power);
             Ok(())
         }
+
+        fn thrust(&self, _location: &str) -> Result<f32, ClientError> {
+            Ok(0.0)
+        }
+
+        fn damage(&self, _part: &str) -> Result<f32, ClientError> {
+            Ok(0.0)
+        }
+
+        fn radio_send(&mut self, _channel: &str, _message: String) -> Result<bool, ClientError> {
+            Ok(true)
+        }
+
+        fn radio_receive(&mut self) -> Result<Option<(String, String)>, ClientError> {
+            Ok(None)
+        }
+
+        fn telemetry(&self) -> Telemetry {
+            Telemetry::default()
+        }
     }
 
     struct Environment;
     impl ProgramEnvironment for Environment {
-        fn is_pressed(&self, char: &str, _mods: ModKey) -> Result<bool, ClientError> {
+        fn is_pressed(&self, char: &str, _mods: Option<ModKey>) -> Result<bool, ClientError> {
             if char.len() != 1 {
                 return Err(ClientError::ValidationFailure {
                     performing: "is_pressed".to_owned(),
@@ -254,4 +312,71 @@ mod tests {
         assert_eq!(client.booster.get("booster_A"), Some(&0.5));
         assert_eq!(client.booster.get("booster_B"), Some(&0.3));
     }
+
+    #[test]
+    fn runtime_should_expose_radio_under_api() {
+        let mut executor = LuaProgramExecutor::new();
+
+        executor
+            .load(
+                r#"
+            function main()
+                if not api.radio.send("formation", "hold") then
+                    return 'dropped'
+                end
+
+                local channel, message = api.radio.receive()
+                if channel ~= nil or message ~= nil then
+                    return 'unexpected message'
+                end
+
+                return ''
+            end
+            "#,
+            )
+            .unwrap();
+
+        let result = executor.execute(&mut Client::default(), &Environment);
+
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn runtime_should_restore_globals_from_snapshot() {
+        let mut executor = LuaProgramExecutor::new();
+
+        executor
+            .load(
+                r#"
+            count = 0
+            function main()
+                count = count + 1
+                return ''
+            end
+            "#,
+            )
+            .unwrap();
+
+        executor
+            .execute(&mut Client::default(), &Environment)
+            .unwrap();
+        let snapshot = executor.snapshot();
+
+        executor
+            .execute(&mut Client::default(), &Environment)
+            .unwrap();
+        executor
+            .execute(&mut Client::default(), &Environment)
+            .unwrap();
+        executor.restore(&snapshot).unwrap();
+
+        let count = executor
+            .snapshot()
+            .globals
+            .into_iter()
+            .find(|(name, _)| name == "count")
+            .map(|(_, value)| value);
+
+        assert_eq!(count, Some(LuaValueSnapshot::Integer(1)));
+    }
 }
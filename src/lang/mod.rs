@@ -1,5 +1,10 @@
 pub mod api;
 pub mod exec;
+pub mod snapshot;
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
 
 #[derive(thiserror::Error, Debug)]
 pub enum ClientError {
@@ -11,9 +16,106 @@ pub enum ClientError {
     },
 }
 
+impl ClientError {
+    /// The client lacks the `part` that performing would need.
+    pub fn unsupported(performing: &str, part: &str) -> Self {
+        Self::ValidationFailure {
+            performing: performing.to_string(),
+            part: part.to_string(),
+            reason: format!("This client has no {part}"),
+        }
+    }
+}
+
+/// Motion of the client in SI units (meters, meters per second, radians).
+#[derive(Clone, Debug, Default)]
+pub struct Telemetry {
+    pub position: (f32, f32),
+    pub velocity: (f32, f32),
+    pub angle: f32,
+    pub angular_velocity: f32,
+}
+
+/// How a docking port lines up with the nearest free port of another body. `misalignment`
+/// is in radians, and `closing_speed` is positive while the ports approach each other.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Alignment {
+    pub distance: f32,
+    pub misalignment: f32,
+    pub closing_speed: f32,
+    pub relative_speed: f32,
+}
+
+/// What the firmware controls. Only boosters, damage and telemetry are required; the rest
+/// fail as unsupported unless the client has the matching part.
 pub trait ProgramClient {
     fn is_valid_booster(&self, name: &str) -> bool;
     fn boost(&mut self, location: &str, power: f32) -> Result<(), ClientError>;
+    fn thrust(&self, location: &str) -> Result<f32, ClientError>;
+    fn damage(&self, part: &str) -> Result<f32, ClientError>;
+    fn telemetry(&self) -> Telemetry;
+
+    fn wheel(&mut self, _torque: f32) -> Result<(), ClientError> {
+        Err(ClientError::unsupported(
+            "spinning the wheel",
+            "reaction wheel",
+        ))
+    }
+
+    fn wheel_speed(&self) -> Result<f32, ClientError> {
+        Err(ClientError::unsupported(
+            "reading the wheel speed",
+            "reaction wheel",
+        ))
+    }
+
+    fn range(&self, _sensor: &str) -> Result<Option<f32>, ClientError> {
+        Err(ClientError::unsupported("ranging", "range sensor"))
+    }
+
+    fn nearest(&self, _kind: &str) -> Result<Option<f32>, ClientError> {
+        Err(ClientError::unsupported(
+            "sensing proximity",
+            "proximity sensor",
+        ))
+    }
+
+    /// Called every tick with the distance to the nearest body of each kind, for clients
+    /// that have a proximity sensor to keep.
+    fn report_proximity(&mut self, _proximity: HashMap<String, Option<f32>>) {}
+
+    fn deploy(&mut self, _payload: &str, _firmware: Option<String>) -> Result<(), ClientError> {
+        Err(ClientError::unsupported("deploying", "payload bay"))
+    }
+
+    fn alignment(&self, _port: &str) -> Result<Option<Alignment>, ClientError> {
+        Err(ClientError::unsupported(
+            "reading alignment",
+            "docking port",
+        ))
+    }
+
+    fn docked(&self, _port: &str) -> Result<bool, ClientError> {
+        Err(ClientError::unsupported(
+            "reading docking status",
+            "docking port",
+        ))
+    }
+
+    fn undock(&mut self, _port: &str) -> Result<(), ClientError> {
+        Err(ClientError::unsupported("undocking", "docking port"))
+    }
+
+    fn radio_send(&mut self, _channel: &str, _message: String) -> Result<bool, ClientError> {
+        Err(ClientError::unsupported("sending over the radio", "radio"))
+    }
+
+    fn radio_receive(&mut self) -> Result<Option<(String, String)>, ClientError> {
+        Err(ClientError::unsupported(
+            "receiving over the radio",
+            "radio",
+        ))
+    }
 }
 
 bitflags::bitflags! {
@@ -0,0 +1,87 @@
+use rlua::{Context, Result as LuaResult, Table, Value};
+use serde::{Deserialize, Serialize};
+
+const MAX_TABLE_DEPTH: usize = 16;
+const SKIPPED_GLOBALS: [&str; 3] = ["_G", "_VERSION", "api"];
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum LuaValueSnapshot {
+    Nil,
+    Boolean(bool),
+    Integer(i64),
+    Number(f64),
+    String(String),
+    Table(Vec<(LuaValueSnapshot, LuaValueSnapshot)>),
+}
+
+impl LuaValueSnapshot {
+    pub fn capture(value: &Value) -> Option<Self> {
+        Self::capture_with_depth(value, 0)
+    }
+
+    fn capture_with_depth(value: &Value, depth: usize) -> Option<Self> {
+        match value {
+            Value::Nil => Some(Self::Nil),
+            Value::Boolean(value) => Some(Self::Boolean(*value)),
+            Value::Integer(value) => Some(Self::Integer(*value)),
+            Value::Number(value) => Some(Self::Number(*value)),
+            Value::String(value) => value.to_str().ok().map(|str| Self::String(str.to_string())),
+            // Tables can refer themselves, so the depth is capped to avoid infinite recursion
+            Value::Table(table) if depth < MAX_TABLE_DEPTH => {
+                let entries = table
+                    .clone()
+                    .pairs::<Value, Value>()
+                    .filter_map(Result::ok)
+                    .filter_map(|(key, value)| {
+                        Some((
+                            Self::capture_with_depth(&key, depth + 1)?,
+                            Self::capture_with_depth(&value, depth + 1)?,
+                        ))
+                    })
+                    .collect();
+
+                Some(Self::Table(entries))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn to_lua<'lua>(&self, ctx: Context<'lua>) -> LuaResult<Value<'lua>> {
+        Ok(match self {
+            Self::Nil => Value::Nil,
+            Self::Boolean(value) => Value::Boolean(*value),
+            Self::Integer(value) => Value::Integer(*value),
+            Self::Number(value) => Value::Number(*value),
+            Self::String(value) => Value::String(ctx.create_string(value)?),
+            Self::Table(entries) => {
+                let table = ctx.create_table()?;
+                for (key, value) in entries {
+                    table.set(key.to_lua(ctx)?, value.to_lua(ctx)?)?;
+                }
+
+                Value::Table(table)
+            }
+        })
+    }
+}
+
+pub fn capture_globals(globals: &Table) -> Vec<(String, LuaValueSnapshot)> {
+    globals
+        .clone()
+        .pairs::<String, Value>()
+        .filter_map(Result::ok)
+        .filter(|(name, _)| !SKIPPED_GLOBALS.contains(&name.as_str()))
+        .filter_map(|(name, value)| Some((name, LuaValueSnapshot::capture(&value)?)))
+        .collect()
+}
+
+pub fn restore_globals<'lua>(
+    ctx: Context<'lua>,
+    globals: &[(String, LuaValueSnapshot)],
+) -> LuaResult<()> {
+    let table = ctx.globals();
+
+    globals
+        .iter()
+        .try_for_each(|(name, value)| table.set(name.as_str(), value.to_lua(ctx)?))
+}
@@ -0,0 +1,8 @@
+pub mod entity;
+pub mod gui;
+pub mod headless;
+pub mod lang;
+pub mod system;
+pub mod theory;
+pub mod traitext;
+pub mod world;
@@ -1,18 +1,13 @@
-pub mod entity;
-pub mod gui;
-pub mod lang;
-pub mod system;
-pub mod theory;
-pub mod traitext;
-pub mod world;
-
-use std::{env, path::PathBuf};
+use std::{env, path::PathBuf, process};
 
 use ggez::{
     conf::{Conf, WindowMode},
-    event, ContextBuilder,
+    event,
+    winit::dpi::LogicalSize,
+    ContextBuilder,
 };
-use system::GameSystem;
+use sateply::headless::{self, HeadlessOptions};
+use sateply::system::GameSystem;
 
 #[tokio::main]
 async fn main() {
@@ -24,17 +19,32 @@ async fn main() {
         PathBuf::from("./assets")
     };
 
+    if env::args().nth(1).as_deref() == Some("run") {
+        let result = HeadlessOptions::parse(env::args().skip(2))
+            .and_then(|options| headless::run(&options, &resource_dir));
+
+        if let Err(err) = result {
+            eprintln!("{err}");
+            process::exit(1);
+        }
+
+        return;
+    }
+
     let config = Conf::new().window_mode(WindowMode {
-        width: 1920.0,
-        height: 1080.0,
+        logical_size: Some(LogicalSize::new(1280.0, 720.0)),
+        min_width: 640.0,
+        min_height: 360.0,
+        resizable: true,
+        resize_on_scale_factor_change: true,
         ..WindowMode::default()
     });
     let (mut ctx, event_loop) = ContextBuilder::new("hello_ggez", "awesome_person")
         .default_conf(config)
-        .add_resource_path(resource_dir)
+        .add_resource_path(&resource_dir)
         .build()
         .unwrap();
 
-    let system = GameSystem::new(&mut ctx).unwrap();
+    let system = GameSystem::new(&mut ctx, &resource_dir).unwrap();
     event::run(ctx, event_loop, system);
 }
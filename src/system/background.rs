@@ -0,0 +1,132 @@
+use ggez::glam::{vec2, Vec2};
+use ggez::graphics::{Canvas, ClampMode, Color, DrawParam, Image, ImageFormat, Rect, Sampler};
+use ggez::{Context, GameResult};
+
+use super::camera::Camera;
+use crate::theory::units::PIXELS_PER_METER;
+
+const TILE: &str = "/tile.png";
+const STAR_FIELD_SIZE: u32 = 512;
+
+/// An image repeated across the whole screen. `parallax` is how much the layer moves with
+/// the camera compared with the world: 0 stays still, 1 moves like the world itself.
+struct Layer {
+    image: Image,
+    parallax: f32,
+    /// Size of a texel on screen at zoom 1, in pixels
+    scale: f32,
+    color: Color,
+}
+
+/// Space behind the world, farthest layer first. It scrolls and zooms with the camera, but
+/// less than the world does, which is what tells how fast the view is moving.
+pub struct Background {
+    layers: Vec<Layer>,
+}
+
+impl Background {
+    pub fn new(ctx: &mut Context) -> GameResult<Self> {
+        let stars = |count: usize, seed: u32| {
+            Image::from_pixels(
+                ctx,
+                &star_field(STAR_FIELD_SIZE, count, seed),
+                ImageFormat::Rgba8UnormSrgb,
+                STAR_FIELD_SIZE,
+                STAR_FIELD_SIZE,
+            )
+        };
+
+        let layers = vec![
+            Layer {
+                image: Image::from_path(ctx, TILE)?,
+                parallax: 0.05,
+                scale: 2.0,
+                color: Color::from_rgb(30, 30, 60),
+            },
+            Layer {
+                image: stars(160, 1),
+                parallax: 0.15,
+                scale: 1.0,
+                color: Color::from_rgb(150, 150, 190),
+            },
+            Layer {
+                image: stars(60, 2),
+                parallax: 0.35,
+                scale: 1.5,
+                color: Color::WHITE,
+            },
+        ];
+
+        Ok(Self { layers })
+    }
+
+    /// Fills the screen, `screen` pixels wide and high, as seen from `camera`.
+    pub fn draw(&self, canvas: &mut Canvas, camera: &Camera, screen: Vec2) {
+        canvas.set_sampler(Sampler {
+            clamp: ClampMode::Repeat,
+            ..Sampler::linear_clamp()
+        });
+
+        for layer in &self.layers {
+            let zoom = camera.zoom().powf(layer.parallax);
+            let texel = layer.scale * zoom;
+            let size = vec2(layer.image.width() as f32, layer.image.height() as f32) * texel;
+
+            // Where the camera is, in tiles of this layer. Texture coordinates wrap, so the
+            // source rectangle can span several tiles.
+            let center = camera.center() * PIXELS_PER_METER * zoom * layer.parallax / size;
+            let span = screen / size;
+            let corner = center - span / 2.0;
+
+            canvas.draw(
+                &layer.image,
+                DrawParam::new()
+                    .src(Rect::new(corner.x, corner.y, span.x, span.y))
+                    .scale(vec2(texel, texel))
+                    .color(layer.color),
+            );
+        }
+
+        canvas.set_default_sampler();
+    }
+}
+
+/// RGBA pixels of a square of `size` with `count` stars of random brightness scattered
+/// over a transparent sky. The same seed always gives the same stars.
+fn star_field(size: u32, count: usize, seed: u32) -> Vec<u8> {
+    let mut state = seed.max(1);
+    let mut next = move || {
+        // xorshift32
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state
+    };
+
+    let mut pixels = vec![0; (size * size * 4) as usize];
+    for _ in 0..count {
+        let (x, y) = (next() % size, next() % size);
+        let brightness = 96 + (next() % 160) as u8;
+
+        let at = ((y * size + x) * 4) as usize;
+        pixels[at..at + 4].copy_from_slice(&[brightness, brightness, brightness, 255]);
+    }
+
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn star_field_should_be_repeatable() {
+        let stars = star_field(64, 20, 7);
+        let lit = stars.chunks(4).filter(|pixel| pixel[3] == 255).count();
+
+        assert_eq!(stars.len(), 64 * 64 * 4);
+        assert!(lit > 0 && lit <= 20);
+        assert_eq!(stars, star_field(64, 20, 7));
+        assert_ne!(stars, star_field(64, 20, 8));
+    }
+}
@@ -0,0 +1,166 @@
+use ggez::glam::Vec2;
+
+use crate::theory::units::{to_meters, PIXELS_PER_METER};
+
+const MIN_ZOOM: f32 = 0.05;
+const MAX_ZOOM: f32 = 8.0;
+/// Zoom factor of one notch of the mouse wheel
+const ZOOM_STEP: f32 = 1.2;
+/// How quickly the camera catches up with where it is heading, per second. Higher is
+/// snappier; the gap shrinks by a factor of e every `1 / SMOOTHING` seconds.
+const SMOOTHING: f32 = 8.0;
+
+/// What part of the world is on screen. The camera eases toward where it was last told to
+/// go, so following a satellite and zooming do not jerk the view.
+#[derive(Debug)]
+pub struct Camera {
+    /// World point at the center of the screen, in meters
+    center: Vec2,
+    zoom: f32,
+    target_center: Vec2,
+    target_zoom: f32,
+    /// Whether the camera keeps the selected satellite at the center
+    pub follow: bool,
+    dragging: bool,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Self {
+            center: Vec2::ZERO,
+            zoom: 1.0,
+            target_center: Vec2::ZERO,
+            target_zoom: 1.0,
+            follow: true,
+            dragging: false,
+        }
+    }
+
+    pub fn center(&self) -> Vec2 {
+        self.center
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// Pixels per meter at the current zoom
+    pub fn scale(&self) -> f32 {
+        PIXELS_PER_METER * self.zoom
+    }
+
+    pub fn world_to_screen(&self, point: Vec2, screen: Vec2) -> Vec2 {
+        (point - self.center) * self.scale() + screen / 2.0
+    }
+
+    pub fn screen_to_world(&self, point: Vec2, screen: Vec2) -> Vec2 {
+        to_world_length(point - screen / 2.0, self.zoom) + self.center
+    }
+
+    /// Heads toward `point`, if the camera is following.
+    pub fn track(&mut self, point: Vec2) {
+        if self.follow && !self.dragging {
+            self.target_center = point;
+        }
+    }
+
+    pub fn start_drag(&mut self) {
+        self.dragging = true;
+    }
+
+    pub fn end_drag(&mut self) {
+        self.dragging = false;
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.dragging
+    }
+
+    /// Moves the view along with the cursor, which moved by `delta` pixels. Panning stops
+    /// following, and is not smoothed so that the world stays under the cursor.
+    pub fn pan(&mut self, delta: Vec2) {
+        self.follow = false;
+        self.center -= to_world_length(delta, self.zoom);
+        self.target_center = self.center;
+    }
+
+    /// Zooms by `notches` of the mouse wheel, keeping the world point under `cursor` in
+    /// place unless the camera is following.
+    pub fn zoom_at(&mut self, notches: f32, cursor: Vec2, screen: Vec2) {
+        let offset = cursor - screen / 2.0;
+        let anchor = self.target_center + to_world_length(offset, self.target_zoom);
+
+        self.target_zoom = (self.target_zoom * ZOOM_STEP.powf(notches)).clamp(MIN_ZOOM, MAX_ZOOM);
+
+        if !self.follow {
+            self.target_center = anchor - to_world_length(offset, self.target_zoom);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.target_center = Vec2::ZERO;
+        self.target_zoom = 1.0;
+        self.follow = true;
+    }
+
+    /// Eases toward the target by `dt` seconds worth of motion.
+    pub fn update(&mut self, dt: f32) {
+        let t = 1.0 - (-SMOOTHING * dt).exp();
+
+        self.center += (self.target_center - self.center) * t;
+        // Zoom eases in log space so that zooming in and out feel alike
+        self.zoom *= (self.target_zoom / self.zoom).powf(t);
+    }
+}
+
+/// Meters spanned by `pixels` on screen at `zoom`
+fn to_world_length(pixels: Vec2, zoom: f32) -> Vec2 {
+    Vec2::new(to_meters(pixels.x), to_meters(pixels.y)) / zoom
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: Vec2 = Vec2::new(1920.0, 1080.0);
+
+    fn settle(camera: &mut Camera) {
+        (0..600).for_each(|_| camera.update(1.0 / 60.0));
+    }
+
+    #[test]
+    fn camera_should_map_screen_and_world_both_ways() {
+        let mut camera = Camera::new();
+        camera.track(Vec2::new(3.0, -2.0));
+        camera.zoom_at(2.0, SCREEN / 2.0, SCREEN);
+        settle(&mut camera);
+
+        let point = Vec2::new(5.0, 1.0);
+        let screen = camera.world_to_screen(point, SCREEN);
+
+        assert!(
+            (camera.world_to_screen(Vec2::new(3.0, -2.0), SCREEN) - SCREEN / 2.0).length() < 1e-2
+        );
+        assert!((camera.screen_to_world(screen, SCREEN) - point).length() < 1e-4);
+    }
+
+    #[test]
+    fn zoom_should_keep_the_point_under_the_cursor() {
+        let mut camera = Camera::new();
+        camera.pan(Vec2::new(100.0, 50.0));
+
+        let cursor = Vec2::new(400.0, 300.0);
+        let before = camera.screen_to_world(cursor, SCREEN);
+
+        camera.zoom_at(-3.0, cursor, SCREEN);
+        settle(&mut camera);
+
+        assert!((camera.screen_to_world(cursor, SCREEN) - before).length() < 1e-3);
+    }
+}
@@ -0,0 +1,103 @@
+pub const TICKS_PER_SECOND: u32 = 60;
+
+const TIME_SCALES: [f32; 7] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0];
+const DEFAULT_SCALE_INDEX: usize = 2;
+
+#[derive(Debug)]
+pub struct SimulationClock {
+    paused: bool,
+    step_requested: bool,
+    scale_index: usize,
+    pending: f32,
+}
+
+impl Default for SimulationClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimulationClock {
+    pub fn new() -> Self {
+        Self {
+            paused: false,
+            step_requested: false,
+            scale_index: DEFAULT_SCALE_INDEX,
+            pending: 0.0,
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn scale(&self) -> f32 {
+        TIME_SCALES[self.scale_index]
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    pub fn request_step(&mut self) {
+        self.paused = true;
+        self.step_requested = true;
+    }
+
+    pub fn faster(&mut self) {
+        self.scale_index = (self.scale_index + 1).min(TIME_SCALES.len() - 1);
+    }
+
+    pub fn slower(&mut self) {
+        self.scale_index = self.scale_index.saturating_sub(1);
+    }
+
+    /// Returns how many simulation ticks should run for one real-time tick.
+    pub fn advance(&mut self) -> u32 {
+        if self.paused {
+            self.pending = 0.0;
+            return std::mem::take(&mut self.step_requested) as u32;
+        }
+
+        self.pending += self.scale();
+        let ticks = self.pending.floor();
+        self.pending -= ticks;
+
+        ticks as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_should_spread_slow_ticks_over_real_ticks() {
+        let mut clock = SimulationClock::new();
+        clock.slower();
+        clock.slower();
+
+        let ticks: Vec<u32> = (0..8).map(|_| clock.advance()).collect();
+
+        assert_eq!(ticks, vec![0, 0, 0, 1, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn clock_should_run_multiple_ticks_when_fast() {
+        let mut clock = SimulationClock::new();
+        (0..10).for_each(|_| clock.faster());
+
+        assert_eq!(clock.scale(), 16.0);
+        assert_eq!(clock.advance(), 16);
+    }
+
+    #[test]
+    fn clock_should_only_step_once_while_paused() {
+        let mut clock = SimulationClock::new();
+        clock.request_step();
+
+        assert!(clock.is_paused());
+        assert_eq!(clock.advance(), 1);
+        assert_eq!(clock.advance(), 0);
+    }
+}
@@ -11,7 +11,7 @@ macro_rules! match_keycode {
     }
 }
 
-fn map_char_to_keycode(key: &str) -> Option<VirtualKeyCode> {
+pub fn map_char_to_keycode(key: &str) -> Option<VirtualKeyCode> {
     let key = key.to_uppercase();
     let key = key.as_str();
 
@@ -47,3 +47,18 @@ impl<'ctx> Environment<'ctx> {
         Self { keyboard_ctx }
     }
 }
+
+/// Stands in for the keyboard on satellites the keyboard is not routed to
+pub struct Unrouted;
+
+impl ProgramEnvironment for Unrouted {
+    fn is_pressed(&self, char: &str, _mods: Option<ModKey>) -> Result<bool, ClientError> {
+        map_char_to_keycode(char)
+            .map(|_| false)
+            .ok_or(ClientError::ValidationFailure {
+                performing: "Key press check".to_string(),
+                part: "char".to_string(),
+                reason: format!("No such key: {char}"),
+            })
+    }
+}
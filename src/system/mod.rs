@@ -1,140 +1,543 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
 use ggez::{
-    event::EventHandler,
+    event::{EventHandler, MouseButton},
     glam::vec2,
-    graphics::{self, Color, Rect, StrokeOptions},
+    graphics::{self, Color, Image, Rect, StrokeOptions},
+    input::keyboard::KeyInput,
     mint::Point2,
+    winit::event::VirtualKeyCode,
     Context, GameError, GameResult,
 };
 
-use crate::entity::satellite::Satellite;
-use crate::entity::DrawOrigin;
+use crate::entity::{DrawInstruction, DrawOrigin, Drawable};
 use crate::gui::GUIEntity;
-use crate::lang::exec::LuaProgramExecutor;
 use crate::system::lang_env::Environment;
-use crate::world::{World, WorldKey, WorldValue};
-use crate::{as_type, entity::Entity};
+use crate::world::{EntityMapEntry, WorldKey, WorldValue};
 
-use self::state::GameState;
+use self::background::Background;
+use self::clock::TICKS_PER_SECOND;
+use self::scene::{Scene, DEFAULT_SCENE};
+use self::session::{Session, QUICKSAVE};
+use self::simulation::Simulation;
+use self::snapshot::SnapshotHistory;
+use self::state::{GameState, PortStatus};
+use self::viewport::Viewport;
 
+pub mod background;
+pub mod camera;
+pub mod clock;
 pub mod lang_env;
+pub mod scene;
+pub mod session;
+pub mod simulation;
+pub mod snapshot;
 pub mod state;
+pub mod viewport;
+
+const SNAPSHOT_CAPACITY: usize = 240;
+const SNAPSHOT_INTERVAL: u64 = 30;
+
+/// How far from the cursor a click still picks a satellite, in meters
+const PICK_RADIUS: f32 = 0.5;
 
 pub struct GameSystem {
-    pub world: World,
+    pub simulation: Simulation,
     pub gui: GUIEntity,
     pub state: GameState,
-    pub lua: LuaProgramExecutor,
-    pub satellite_key: WorldKey,
+    pub history: SnapshotHistory,
+    /// What each entity drew last, in physical pixels
+    pub entity_images: HashMap<WorldKey, Image>,
+    pub background: Background,
 }
 
 impl GameSystem {
-    pub fn new(ctx: &mut ggez::Context) -> GameResult<Self> {
-        let mut world = World::default();
-        let satellite_key = *world.insert(ctx, Satellite::new().typed()).0;
+    /// `resource_dir` is the assets directory, which the default scene is loaded from.
+    pub fn new(ctx: &mut ggez::Context, resource_dir: &Path) -> GameResult<Self> {
+        let scene_path = resource_dir.join(DEFAULT_SCENE);
+        let scene = Scene::parse(&fs::read_to_string(&scene_path)?)
+            .map_err(|err| GameError::ResourceLoadError(err.to_string()))?;
+
+        let mut state = GameState::new(ctx)?;
+        let base = scene_path.parent().unwrap_or(Path::new("."));
+        let simulation = build_scene(ctx, &mut state, &scene, base)?;
 
-        Ok(Self {
-            world,
-            state: GameState::new(ctx)?,
+        let mut system = Self {
+            simulation: Simulation::empty(),
+            state,
             gui: GUIEntity::new(ctx),
-            lua: LuaProgramExecutor::new(),
-            satellite_key,
-        })
+            history: SnapshotHistory::new(SNAPSHOT_CAPACITY, SNAPSHOT_INTERVAL),
+            entity_images: HashMap::new(),
+            background: Background::new(ctx)?,
+        };
+        system.start(simulation);
+        system.gui.resize(&system.state.viewport);
+
+        Ok(system)
     }
 
-    fn update_entities(&mut self, ctx: &mut ggez::Context) {
-        self.world.update_all_entity(ctx).unwrap();
+    /// Replaces the running simulation, forgetting everything about the previous one.
+    fn start(&mut self, simulation: Simulation) {
+        self.simulation = simulation;
+        self.entity_images.clear();
+        self.history = SnapshotHistory::new(SNAPSHOT_CAPACITY, SNAPSHOT_INTERVAL);
+
+        self.state.fleet.satellites = self.simulation.programmables.clone();
+        self.state.fleet.selected = self.simulation.selected;
+        self.state.fleet.routing = self.simulation.routing;
+        self.state.health.clear();
+        self.state.docking.clear();
+
+        self.record_snapshot();
     }
 
-    fn update_lua(&mut self, ctx: &mut ggez::Context) {
-        if let Some(program) = &self.state.next_lua_program {
-            self.lua.load(program).unwrap();
-            self.state.next_lua_program = None;
+    fn handle_scene_requests(&mut self, ctx: &mut ggez::Context) {
+        if let Some(path) = self.state.scene.load.take() {
+            let base = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+            let result = fs::read_to_string(&path)
+                .map_err(GameError::from)
+                .and_then(|source| {
+                    Scene::parse(&source)
+                        .map_err(|err| GameError::ResourceLoadError(err.to_string()))
+                })
+                .and_then(|scene| build_scene(ctx, &mut self.state, &scene, &base));
+
+            match result {
+                Ok(simulation) => {
+                    self.start(simulation);
+                    self.state.scene.error = None;
+                }
+                Err(err) => self.state.scene.error = Some(err.to_string()),
+            }
         }
 
-        let result = self.lua.execute(
-            as_type!(
-                &mut self.world.get_mut(&self.satellite_key).unwrap().entity,
-                Satellite
-            )
-            .unwrap(),
-            &Environment::new(&ctx.keyboard),
-        );
+        if let Some(path) = self.state.scene.save.take() {
+            let result = Scene::capture(&self.simulation, &path)
+                .to_ron()
+                .map_err(|err| err.to_string())
+                .and_then(|source| fs::write(&path, source).map_err(|err| err.to_string()));
+
+            self.state.scene.error = result.err();
+        }
+    }
+
+    fn handle_session_requests(&mut self, ctx: &mut ggez::Context) {
+        if let Some(path) = self.state.session.load.take() {
+            let registry = self.simulation.world.registry();
+            let result = fs::read_to_string(&path)
+                .map_err(|err| err.to_string())
+                .and_then(|source| Session::parse(&source, registry).map_err(|err| err.to_string()))
+                .and_then(|session| session.resume(registry).map_err(|err| err.to_string()))
+                .and_then(|simulation| {
+                    load_sprites(ctx, &mut self.state, &simulation)
+                        .map_err(|err| err.to_string())?;
+                    Ok(simulation)
+                });
+
+            match result {
+                Ok(simulation) => {
+                    self.start(simulation);
+                    self.state.session.error = None;
+                }
+                Err(err) => self.state.session.error = Some(err),
+            }
+        }
+
+        if let Some(path) = self.state.session.save.take() {
+            let result = Session::capture(&self.simulation)
+                .and_then(|session| session.to_ron())
+                .map_err(|err| err.to_string())
+                .and_then(|source| fs::write(&path, source).map_err(|err| err.to_string()));
+
+            self.state.session.error = result.err();
+        }
+    }
+
+    fn record_snapshot(&mut self) {
+        self.history.push(self.simulation.capture());
+        self.state.rewind.available_ticks = self.history.ticks();
+    }
+
+    fn rewind(&mut self) {
+        let Some(tick) = self.state.rewind.requested.take() else {
+            return;
+        };
+        let Some(snapshot) = self.history.find(tick).cloned() else {
+            return;
+        };
+
+        let result = self
+            .simulation
+            .restore(&snapshot, self.state.rewind.keep_program);
+        self.state.fleet.satellites = self.simulation.programmables.clone();
+        self.history.forget_after(tick);
+        self.state.rewind.available_ticks = self.history.ticks();
 
         #[cfg(debug_assertions)]
         if let Err(err) = result {
             println!("{err}");
         }
     }
+
+    fn tick_simulation(&mut self, ctx: &mut ggez::Context) {
+        if let Some((key, path, program)) = self.state.next_lua_program.take() {
+            let result = self.simulation.load_firmware(&key, &path, &program);
+
+            #[cfg(debug_assertions)]
+            if let Err(err) = result {
+                println!("{err}");
+            }
+        }
+
+        self.simulation.selected = self.state.fleet.selected;
+        self.simulation.routing = self.state.fleet.routing;
+
+        let errors = self.simulation.step(&Environment::new(&ctx.keyboard));
+        self.state.fleet.satellites = self.simulation.programmables.clone();
+
+        #[cfg(debug_assertions)]
+        errors
+            .iter()
+            .for_each(|(key, err)| println!("[{key}] {err}"));
+
+        let selected = self.state.fleet.selected;
+        if let Some(satellite) = selected.and_then(|key| self.simulation.satellite(&key)) {
+            self.state.health = satellite
+                .health
+                .iter()
+                .map(|part| (part.name.clone(), part.health))
+                .collect();
+
+            self.state.docking = satellite
+                .design
+                .ports
+                .iter()
+                .map(|port| {
+                    let docked = satellite.docks.iter().any(|dock| dock.port == port.name);
+                    let status = match satellite.alignment.get(&port.name).copied().flatten() {
+                        _ if docked => PortStatus::Docked,
+                        Some(alignment) => PortStatus::Aligning(alignment),
+                        None => PortStatus::Free,
+                    };
+
+                    (port.name.clone(), status)
+                })
+                .collect();
+        }
+
+        if self.history.should_capture(self.simulation.tick) {
+            self.record_snapshot();
+        }
+    }
+}
+
+/// Builds the scene with designs and sprites from the game's resources, and loads the
+/// sprites of its satellites and payloads for drawing.
+fn build_scene(
+    ctx: &mut ggez::Context,
+    state: &mut GameState,
+    scene: &Scene,
+    base: &Path,
+) -> GameResult<Simulation> {
+    let read_asset = |path: &str| {
+        let mut bytes = Vec::new();
+        ctx.fs
+            .open(format!("/{path}"))
+            .map_err(|err| io::Error::new(io::ErrorKind::NotFound, err.to_string()))?
+            .read_to_end(&mut bytes)?;
+
+        Ok(bytes)
+    };
+
+    let simulation = scene
+        .build(read_asset, base)
+        .map_err(|err| GameError::ResourceLoadError(err.to_string()))?;
+    load_sprites(ctx, state, &simulation)?;
+
+    Ok(simulation)
+}
+
+fn load_sprites(
+    ctx: &mut ggez::Context,
+    state: &mut GameState,
+    simulation: &Simulation,
+) -> GameResult {
+    simulation
+        .programmables
+        .iter()
+        .filter_map(|key| simulation.satellite(key))
+        .chain(simulation.prototypes.values())
+        .try_for_each(|satellite| state.load_sprite(ctx, &satellite.design.sprite))
+}
+
+/// Draws the entity into its image, at the scale factor of the window. The image is
+/// enlarged and drawn again when the drawing does not fit.
+fn render_entity(
+    ctx: &mut ggez::Context,
+    image: &mut Image,
+    drawable: &dyn Drawable,
+    state: &GameState,
+) -> GameResult<DrawInstruction> {
+    let scale_factor = state.viewport.scale_factor;
+    let draw = |ctx: &mut ggez::Context, image: &Image| {
+        let mut canvas =
+            graphics::Canvas::from_image(ctx, image.clone(), Color::from_rgba(0, 0, 0, 0));
+        canvas.set_screen_coordinates(Rect::new(
+            0.0,
+            0.0,
+            image.width() as f32 / scale_factor,
+            image.height() as f32 / scale_factor,
+        ));
+        let instruction = drawable.draw(&mut canvas, state)?;
+        canvas.finish(ctx)?;
+
+        GameResult::Ok(instruction)
+    };
+
+    let instruction = draw(ctx, image)?;
+    let needed = (instruction.size * scale_factor).ceil();
+    if needed.x <= image.width() as f32 && needed.y <= image.height() as f32 {
+        return Ok(instruction);
+    }
+
+    *image = Image::new_canvas_image(
+        &ctx.gfx,
+        ctx.gfx.surface_format(),
+        (needed.x as u32).max(image.width()),
+        (needed.y as u32).max(image.height()),
+        1,
+    );
+    draw(ctx, image)
+}
+
+fn quicksave_path(ctx: &Context) -> GameResult<PathBuf> {
+    let dir = ctx.fs.user_data_dir();
+    fs::create_dir_all(dir)?;
+
+    Ok(dir.join(QUICKSAVE))
 }
 
 impl EventHandler<GameError> for GameSystem {
     fn update(&mut self, ctx: &mut ggez::Context) -> Result<(), GameError> {
         self.gui.update(&mut self.state, ctx)?;
+        self.handle_scene_requests(ctx);
+        self.handle_session_requests(ctx);
+        self.rewind();
 
-        while ctx.time.check_update_time(60) {
-            self.update_lua(ctx);
-            self.update_entities(ctx);
+        let selected = self.state.fleet.selected;
+        if let Some(telemetry) = selected.and_then(|key| self.simulation.telemetry(&key)) {
+            self.state.camera.track(telemetry.position.into());
+        }
+        self.state.camera.update(ctx.time.delta().as_secs_f32());
+
+        while ctx.time.check_update_time(TICKS_PER_SECOND) {
+            for _ in 0..self.state.clock.advance() {
+                self.tick_simulation(ctx);
+            }
         }
 
         Ok(())
     }
 
     fn draw(&mut self, ctx: &mut ggez::Context) -> Result<(), GameError> {
-        let mut canvas = graphics::Canvas::from_frame(ctx, Color::from([0.0, 0.0, 0.2, 1.0]));
+        let viewport = self.state.viewport;
+        let screen = viewport.logical_size();
 
-        self.world.iter_mut_entity().try_for_each(
-            |WorldValue {
-                 entity,
-                 ref mut screen_image,
-             }| {
-                let mut img_canvas = graphics::Canvas::from_image(
-                    ctx,
-                    screen_image.image(ctx),
-                    graphics::Color::from_rgba(0, 0, 0, 0),
-                );
+        let mut canvas = graphics::Canvas::from_frame(ctx, Color::BLACK);
+        canvas.set_screen_coordinates(viewport.logical_rect());
+        self.background
+            .draw(&mut canvas, &self.state.camera, screen);
 
-                let draw = entity.inner().draw(&mut img_canvas, &self.state)?;
+        let world = &self.simulation.world;
+        self.entity_images.retain(|key, _| world.contains_key(key));
 
-                img_canvas.finish(ctx)?;
+        world.iter_entity().try_for_each(
+            |EntityMapEntry {
+                 key,
+                 value: WorldValue { entity },
+             }| {
+                let Some(drawable) = entity.as_drawable() else {
+                    return Ok(());
+                };
+
+                let image = self.entity_images.entry(key).or_insert_with(|| {
+                    Image::new_canvas_image(&ctx.gfx, ctx.gfx.surface_format(), 1, 1, 1)
+                });
+                let draw = render_entity(ctx, image, drawable, &self.state)?;
 
-                let offset = vec2(draw.size.x / 2.0, draw.size.y / 2.0);
-                let offset_screen = match draw.draw_origin {
-                    DrawOrigin::World => vec2(1920.0 / 2.0, 1080.0 / 2.0),
-                    DrawOrigin::ScreenAbsolute => vec2(0.0, 0.0),
+                let camera = &self.state.camera;
+                let (dest, scale) = match draw.draw_origin {
+                    DrawOrigin::World => {
+                        (camera.world_to_screen(draw.position, screen), camera.zoom())
+                    }
+                    DrawOrigin::ScreenAbsolute => (draw.position + draw.size / 2.0, 1.0),
                 };
 
+                // The image is in physical pixels, and may be larger than the drawing
+                let image_scale = scale / viewport.scale_factor;
+                let drawn = draw.size * viewport.scale_factor;
                 canvas.draw(
-                    &screen_image.image(ctx),
+                    &*image,
                     graphics::DrawParam::new()
                         .src(Rect::new(
                             0.0,
                             0.0,
-                            draw.size.x / 1920.0,
-                            draw.size.y / 1080.0,
+                            drawn.x / image.width() as f32,
+                            drawn.y / image.height() as f32,
                         ))
-                        .dest(draw.position + offset + offset_screen)
+                        .dest(dest)
+                        .scale(vec2(image_scale, image_scale))
                         .rotation(draw.angle)
                         .offset(Point2 { x: 0.5, y: 0.5 }), // .color(Color::from((255, 255, 255, 128)))
                 );
 
+                let size = draw.size * scale;
+                let top_left = dest - size / 2.0;
                 canvas.draw(
                     &graphics::Mesh::new_rectangle(
                         &ctx.gfx,
                         graphics::DrawMode::Stroke(StrokeOptions::default()),
-                        Rect::new(draw.position.x, draw.position.y, draw.size.x, draw.size.y),
+                        Rect::new(top_left.x, top_left.y, size.x, size.y),
                         Color::RED,
                     )?,
-                    graphics::DrawParam::from(offset_screen),
+                    graphics::DrawParam::default(),
                 );
 
                 GameResult::Ok(())
             },
         )?;
 
+        // The GUI scales itself by the scale factor
+        canvas.set_screen_coordinates(viewport.physical_rect());
         self.gui.draw(&mut canvas, &self.state)?;
         canvas.finish(ctx)
     }
 
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        input: KeyInput,
+        repeated: bool,
+    ) -> Result<(), GameError> {
+        if repeated {
+            return Ok(());
+        }
+
+        match input.keycode {
+            Some(VirtualKeyCode::F5) => self.state.clock.toggle_pause(),
+            Some(VirtualKeyCode::F6) => self.state.clock.request_step(),
+            Some(VirtualKeyCode::F7) => self.state.clock.slower(),
+            Some(VirtualKeyCode::F8) => self.state.clock.faster(),
+            Some(VirtualKeyCode::F9) => self.state.session.save = Some(quicksave_path(ctx)?),
+            Some(VirtualKeyCode::F10) => self.state.session.load = Some(quicksave_path(ctx)?),
+            // Overriding the handler drops ggez's own Escape-to-quit
+            Some(VirtualKeyCode::Escape) => ctx.request_quit(),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn mouse_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> Result<(), GameError> {
+        if self.gui.is_pointer_over() {
+            return Ok(());
+        }
+
+        if matches!(button, MouseButton::Right | MouseButton::Middle) {
+            self.state.camera.start_drag();
+            return Ok(());
+        }
+        if button != MouseButton::Left {
+            return Ok(());
+        }
+
+        let viewport = self.state.viewport;
+        let at = self
+            .state
+            .camera
+            .screen_to_world(viewport.to_logical(vec2(x, y)), viewport.logical_size());
+        let picked = self
+            .simulation
+            .world
+            .query_radius(at.into(), PICK_RADIUS)
+            .into_iter()
+            .find(|key| self.state.fleet.satellites.contains(key));
+
+        if picked.is_some() {
+            self.state.fleet.selected = picked;
+        }
+
+        Ok(())
+    }
+
+    fn mouse_button_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: MouseButton,
+        _x: f32,
+        _y: f32,
+    ) -> Result<(), GameError> {
+        if matches!(button, MouseButton::Right | MouseButton::Middle) {
+            self.state.camera.end_drag();
+        }
+
+        Ok(())
+    }
+
+    fn mouse_motion_event(
+        &mut self,
+        _ctx: &mut Context,
+        _x: f32,
+        _y: f32,
+        dx: f32,
+        dy: f32,
+    ) -> Result<(), GameError> {
+        if self.state.camera.is_dragging() {
+            let delta = self.state.viewport.to_logical(vec2(dx, dy));
+            self.state.camera.pan(delta);
+        }
+
+        Ok(())
+    }
+
+    fn mouse_wheel_event(&mut self, ctx: &mut Context, _x: f32, y: f32) -> Result<(), GameError> {
+        if self.gui.is_pointer_over() {
+            return Ok(());
+        }
+
+        let viewport = self.state.viewport;
+        let cursor = ctx.mouse.position();
+        self.state.camera.zoom_at(
+            y,
+            viewport.to_logical(vec2(cursor.x, cursor.y)),
+            viewport.logical_size(),
+        );
+
+        Ok(())
+    }
+
+    /// Also raised when the window moves to a screen with another scale factor
+    fn resize_event(
+        &mut self,
+        ctx: &mut Context,
+        _width: f32,
+        _height: f32,
+    ) -> Result<(), GameError> {
+        self.state.viewport = Viewport::of_window(ctx);
+        self.gui.resize(&self.state.viewport);
+
+        Ok(())
+    }
+
     fn text_input_event(&mut self, _ctx: &mut Context, character: char) -> Result<(), GameError> {
         self.gui.on_text_input(character);
         Ok(())
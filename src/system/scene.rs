@@ -0,0 +1,539 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use ggez::graphics::Color;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::entity::asteroid::Asteroid;
+use crate::entity::design::{DesignError, SatelliteDesign};
+use crate::entity::modular::ModularCraft;
+use crate::entity::planet::Planet;
+use crate::entity::satellite::Satellite;
+use crate::entity::station::Station;
+use crate::entity::waypoint::Waypoint;
+use crate::lang::exec::ExecutionError;
+use crate::theory::geometry::Transform;
+use crate::theory::physics::{ColliderShape, Motion};
+use crate::theory::units::to_meters;
+use crate::world::WorldKey;
+
+use super::simulation::Simulation;
+
+pub const DEFAULT_SCENE: &str = "scenes/default.ron";
+
+const SPRITE_ALPHA_THRESHOLD: u8 = 16;
+
+#[derive(thiserror::Error, Debug)]
+pub enum SceneError {
+    #[error("Could not parse the scene: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+
+    #[error("Could not write the scene: {0}")]
+    Serialize(#[from] ron::Error),
+
+    #[error("Could not read '{path}': {error}")]
+    Io { path: String, error: io::Error },
+
+    #[error("Could not load the design '{name}': {error}")]
+    Design { name: String, error: DesignError },
+
+    #[error("Could not load the sprite: {0}")]
+    Sprite(#[from] image::ImageError),
+
+    #[error("Could not load the firmware '{path}': {error}")]
+    Firmware {
+        path: PathBuf,
+        error: ExecutionError,
+    },
+}
+
+/// A world layout stored as RON. Satellites refer to their design by name, which is
+/// looked up as `designs/<name>.ron` among the assets, and to their firmware by a path
+/// relative to the scene file.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Scene {
+    pub entities: Vec<SceneEntity>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum SceneEntity {
+    Satellite {
+        design: String,
+        transform: Transform,
+        #[serde(default)]
+        motion: Motion,
+        #[serde(default)]
+        firmware: Option<PathBuf>,
+        /// Whether the satellite starts as the target of the keyboard and the GUI
+        #[serde(default)]
+        selected: bool,
+    },
+    ModularCraft {
+        transform: Transform,
+        #[serde(default)]
+        motion: Motion,
+        #[serde(default)]
+        firmware: Option<PathBuf>,
+        /// The pose of every part, in the craft's order; the parts are laid out rigidly
+        /// around `transform` when omitted.
+        #[serde(default)]
+        parts: Vec<PartPose>,
+    },
+    Planet {
+        location: (f32, f32),
+        radius: f32,
+        color: (u8, u8, u8),
+    },
+    Station {
+        transform: Transform,
+        #[serde(default)]
+        motion: Motion,
+    },
+    Asteroid {
+        transform: Transform,
+        radius: f32,
+        #[serde(default)]
+        motion: Motion,
+    },
+    Waypoint {
+        name: String,
+        location: (f32, f32),
+        radius: f32,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PartPose {
+    pub transform: Transform,
+    #[serde(default)]
+    pub motion: Motion,
+}
+
+pub fn design_path(name: &str) -> String {
+    format!("designs/{name}.ron")
+}
+
+impl Scene {
+    pub fn parse(source: &str) -> Result<Self, SceneError> {
+        Ok(ron::from_str(source)?)
+    }
+
+    pub fn to_ron(&self) -> Result<String, SceneError> {
+        Ok(ron::ser::to_string_pretty(self, PrettyConfig::default())?)
+    }
+
+    /// Describes the current state of the simulation, to be written to `destination`.
+    /// Firmware paths are made relative to it, and entities of kinds a scene cannot
+    /// describe are left out.
+    pub fn capture(simulation: &Simulation, destination: &Path) -> Self {
+        let dir = destination
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let firmware = |key: &WorldKey| {
+            simulation
+                .firmware
+                .get(key)
+                .map(|path| relative_to(path, dir))
+        };
+
+        let entities = simulation
+            .world
+            .iter_entity()
+            .filter_map(|entry| {
+                let entity = entry.value.entity.as_ref();
+
+                if let Some(satellite) = entity.downcast_ref::<Satellite>() {
+                    return Some(SceneEntity::Satellite {
+                        design: satellite.design.name.clone(),
+                        transform: satellite.transform.clone(),
+                        motion: satellite.motion.clone(),
+                        firmware: firmware(&entry.key),
+                        selected: simulation.selected == Some(entry.key),
+                    });
+                }
+
+                if let Some(craft) = entity.downcast_ref::<ModularCraft>() {
+                    return Some(SceneEntity::ModularCraft {
+                        transform: craft.parts[0].transform.clone(),
+                        motion: craft.parts[0].motion.clone(),
+                        firmware: firmware(&entry.key),
+                        parts: craft
+                            .parts
+                            .iter()
+                            .map(|part| PartPose {
+                                transform: part.transform.clone(),
+                                motion: part.motion.clone(),
+                            })
+                            .collect(),
+                    });
+                }
+
+                if let Some(planet) = entity.downcast_ref::<Planet>() {
+                    return Some(SceneEntity::Planet {
+                        location: planet.transform.location,
+                        radius: planet.radius,
+                        color: planet.color.to_rgb(),
+                    });
+                }
+
+                if let Some(station) = entity.downcast_ref::<Station>() {
+                    return Some(SceneEntity::Station {
+                        transform: station.transform.clone(),
+                        motion: station.motion.clone(),
+                    });
+                }
+
+                if let Some(asteroid) = entity.downcast_ref::<Asteroid>() {
+                    return Some(SceneEntity::Asteroid {
+                        transform: asteroid.transform.clone(),
+                        radius: asteroid.radius,
+                        motion: asteroid.motion.clone(),
+                    });
+                }
+
+                if let Some(waypoint) = entity.downcast_ref::<Waypoint>() {
+                    return Some(SceneEntity::Waypoint {
+                        name: waypoint.name.clone(),
+                        location: waypoint.location,
+                        radius: waypoint.radius,
+                    });
+                }
+
+                None
+            })
+            .collect();
+
+        Self { entities }
+    }
+
+    /// Names of the satellite designs the scene refers to, without duplicates.
+    pub fn designs(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .entities
+            .iter()
+            .filter_map(|entity| match entity {
+                SceneEntity::Satellite { design, .. } => Some(design.as_str()),
+                _ => None,
+            })
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+
+        names
+    }
+
+    /// Builds a simulation out of the scene. `read_asset` reads designs and sprites by
+    /// their path under the assets, and firmware paths are resolved against `base`.
+    pub fn build(
+        &self,
+        read_asset: impl Fn(&str) -> io::Result<Vec<u8>>,
+        base: &Path,
+    ) -> Result<Simulation, SceneError> {
+        let read = |path: &str| {
+            read_asset(path).map_err(|error| SceneError::Io {
+                path: path.to_string(),
+                error,
+            })
+        };
+
+        // Designs of the scene along with those their payloads are built from
+        let mut satellites: HashMap<String, Satellite> = HashMap::new();
+        let mut pending: Vec<String> = self.designs().into_iter().map(String::from).collect();
+        while let Some(name) = pending.pop() {
+            if satellites.contains_key(&name) {
+                continue;
+            }
+
+            let source = read(&design_path(&name))?;
+            let design =
+                SatelliteDesign::parse(&String::from_utf8_lossy(&source)).map_err(|error| {
+                    SceneError::Design {
+                        name: name.clone(),
+                        error,
+                    }
+                })?;
+            pending.extend(design.payloads.iter().map(|payload| payload.design.clone()));
+
+            let mut satellite = Satellite::new(design);
+            if satellite.design.shape.is_none() {
+                satellite = satellite.with_shape(ColliderShape::from_sprite(
+                    &read(&satellite.design.sprite)?,
+                    to_meters(satellite.design.sprite_scale),
+                    SPRITE_ALPHA_THRESHOLD,
+                )?);
+            }
+
+            satellites.insert(name, satellite);
+        }
+
+        let mut simulation = Simulation::empty();
+        simulation.prototypes = satellites.clone();
+        for entity in &self.entities {
+            match entity.clone() {
+                SceneEntity::Satellite {
+                    design,
+                    transform,
+                    motion,
+                    firmware,
+                    selected,
+                } => {
+                    let mut satellite = satellites[&design].clone();
+                    satellite.transform = transform;
+                    satellite.motion = motion;
+
+                    let key = simulation.add_programmable(satellite);
+                    if selected || simulation.selected.is_none() {
+                        simulation.selected = Some(key);
+                    }
+
+                    if let Some(path) = firmware {
+                        load_firmware(&mut simulation, &key, base, path)?;
+                    }
+                }
+                SceneEntity::ModularCraft {
+                    transform,
+                    motion,
+                    firmware,
+                    parts,
+                } => {
+                    let mut craft = ModularCraft::sample(transform);
+                    craft.set_motion(motion);
+
+                    if !parts.is_empty() {
+                        if parts.len() != craft.parts.len() {
+                            return Err(SceneError::Design {
+                                name: "modular_craft".to_string(),
+                                error: DesignError::Invalid {
+                                    part: "parts".to_string(),
+                                    reason: format!(
+                                        "{} poses for a craft of {} parts",
+                                        parts.len(),
+                                        craft.parts.len()
+                                    ),
+                                },
+                            });
+                        }
+
+                        craft.parts.iter_mut().zip(parts).for_each(|(part, pose)| {
+                            part.transform = pose.transform;
+                            part.motion = pose.motion;
+                        });
+                    }
+
+                    let key = simulation.add_programmable(craft);
+
+                    if let Some(path) = firmware {
+                        load_firmware(&mut simulation, &key, base, path)?;
+                    }
+                }
+                SceneEntity::Planet {
+                    location,
+                    radius,
+                    color,
+                } => {
+                    simulation
+                        .world
+                        .insert(Planet::new(location, radius, Color::from(color)));
+                }
+                SceneEntity::Station { transform, motion } => {
+                    let mut station = Station::new(transform);
+                    station.motion = motion;
+
+                    simulation.world.insert(station);
+                }
+                SceneEntity::Asteroid {
+                    transform,
+                    radius,
+                    motion,
+                } => {
+                    let mut asteroid = Asteroid::new(transform.location, radius, motion);
+                    asteroid.transform = transform;
+
+                    simulation.world.insert(asteroid);
+                }
+                SceneEntity::Waypoint {
+                    name,
+                    location,
+                    radius,
+                } => {
+                    simulation
+                        .world
+                        .insert(Waypoint::new(&name, location, radius));
+                }
+            }
+        }
+
+        Ok(simulation)
+    }
+}
+
+fn load_firmware(
+    simulation: &mut Simulation,
+    key: &WorldKey,
+    base: &Path,
+    path: PathBuf,
+) -> Result<(), SceneError> {
+    let path = base.join(path);
+    let program = std::fs::read_to_string(&path).map_err(|error| SceneError::Io {
+        path: path.display().to_string(),
+        error,
+    })?;
+
+    simulation
+        .load_firmware(key, &path, &program)
+        .map_err(|error| SceneError::Firmware { path, error })
+}
+
+/// `path` as reached from `dir`, where both are taken from the working directory. Paths
+/// that share no root with `dir` are left as they are.
+fn relative_to(path: &Path, dir: &Path) -> PathBuf {
+    let (Ok(absolute), Ok(dir)) = (std::path::absolute(path), std::path::absolute(dir)) else {
+        return path.to_path_buf();
+    };
+
+    let common = absolute
+        .components()
+        .zip(dir.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    if common == 0 {
+        return path.to_path_buf();
+    }
+
+    dir.components()
+        .skip(common)
+        .map(|_| Component::ParentDir)
+        .chain(absolute.components().skip(common))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scene_should_be_parsed_with_defaults() {
+        let scene = Scene::parse(
+            r#"(
+                entities: [
+                    Satellite(design: "satellite", transform: (location: (1.0, 2.0), angle: 0.0)),
+                    Satellite(design: "satellite", transform: (location: (4.0, 2.0), angle: 0.0)),
+                    Waypoint(name: "alpha", location: (0.0, -8.0), radius: 1.5),
+                    ModularCraft(
+                        transform: (location: (0.0, 6.0), angle: 0.0),
+                        motion: (linear: (1.0, 0.0), angular: 0.0),
+                    ),
+                ],
+            )"#,
+        )
+        .unwrap();
+
+        assert_eq!(scene.designs(), vec!["satellite"]);
+        assert!(matches!(
+            &scene.entities[0],
+            SceneEntity::Satellite { firmware: None, selected: false, motion, .. }
+                if motion.linear == (0.0, 0.0)
+        ));
+
+        let reparsed = Scene::parse(&scene.to_ron().unwrap()).unwrap();
+        assert_eq!(reparsed.entities.len(), 4);
+        assert!(matches!(
+            &reparsed.entities[3],
+            SceneEntity::ModularCraft { firmware: None, motion, .. } if motion.linear == (1.0, 0.0)
+        ));
+    }
+
+    #[test]
+    fn scene_should_build_every_kind_of_body() {
+        let scene = Scene::parse(
+            r#"(
+                entities: [
+                    Planet(location: (0.0, 0.0), radius: 4.0, color: (40, 90, 200)),
+                    Asteroid(
+                        transform: (location: (12.0, 0.0), angle: 0.5),
+                        radius: 0.8,
+                        motion: (linear: (0.0, 1.0), angular: 0.3),
+                    ),
+                    Station(transform: (location: (0.0, 20.0), angle: 0.0)),
+                    Waypoint(name: "alpha", location: (0.0, -8.0), radius: 1.5),
+                ],
+            )"#,
+        )
+        .unwrap();
+
+        let no_assets = |path: &str| -> io::Result<Vec<u8>> {
+            Err(io::Error::new(io::ErrorKind::NotFound, path))
+        };
+        let simulation = scene.build(no_assets, Path::new(".")).unwrap();
+
+        let kinds: Vec<_> = simulation
+            .world
+            .iter_entity()
+            .map(|entry| entry.value.entity.kind())
+            .collect();
+        assert_eq!(kinds, vec!["planet", "asteroid", "station", "waypoint"]);
+        assert!(simulation.programmables.is_empty());
+
+        let asteroid = simulation
+            .world
+            .iter_entity()
+            .find_map(|entry| entry.value.entity.downcast_ref::<Asteroid>())
+            .unwrap();
+        assert_eq!(asteroid.transform.angle, 0.5);
+        assert_eq!(asteroid.motion.angular, 0.3);
+    }
+
+    #[test]
+    fn captured_craft_should_keep_the_pose_of_every_part() {
+        let mut simulation = Simulation::empty();
+        let mut craft = ModularCraft::sample(Transform::new((0.0, 6.0), 0.0));
+        craft.parts[3].transform = Transform::new((0.4, 4.2), 0.3);
+        craft.parts[3].motion.angular = 0.2;
+        let key = simulation.add_programmable(craft);
+        simulation
+            .firmware
+            .insert(key, PathBuf::from("scenes/firmware/hover.lua"));
+
+        let mut scene = Scene::capture(&simulation, Path::new("scenes/saved/level.ron"));
+        assert!(matches!(
+            &scene.entities[0],
+            SceneEntity::ModularCraft { firmware: Some(path), parts, .. }
+                if path == Path::new("../firmware/hover.lua") && parts.len() == 4
+        ));
+
+        let no_assets = |path: &str| -> io::Result<Vec<u8>> {
+            Err(io::Error::new(io::ErrorKind::NotFound, path))
+        };
+        let SceneEntity::ModularCraft { firmware, .. } = &mut scene.entities[0] else {
+            unreachable!();
+        };
+        *firmware = None;
+        let rebuilt = scene.build(no_assets, Path::new(".")).unwrap();
+
+        let key = rebuilt.programmables[0];
+        let craft = rebuilt.world.get(&key).unwrap().entity.as_ref();
+        let boom = &craft.downcast_ref::<ModularCraft>().unwrap().parts[3];
+        assert_eq!(boom.transform.location, (0.4, 4.2));
+        assert_eq!(boom.transform.angle, 0.3);
+        assert_eq!(boom.motion.angular, 0.2);
+    }
+
+    #[test]
+    fn firmware_path_should_be_relative_to_the_scene() {
+        assert_eq!(
+            relative_to(Path::new("scenes/firmware/hover.lua"), Path::new("scenes")),
+            Path::new("firmware/hover.lua")
+        );
+        assert_eq!(
+            relative_to(Path::new("firmware/hover.lua"), Path::new("scenes/saved")),
+            Path::new("../../firmware/hover.lua")
+        );
+        assert_eq!(
+            relative_to(Path::new("hover.lua"), Path::new(".")),
+            Path::new("hover.lua")
+        );
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use ron::ser::PrettyConfig;
+use serde::de::{self, DeserializeSeed, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::entity::registry::{EntityRegistry, RegistryError};
+use crate::entity::satellite::Satellite;
+use crate::lang::exec::{ExecutionError, LuaProgramExecutor, ProgramSnapshot};
+use crate::world::{SavedWorld, SavedWorldSeed, WorldKey};
+
+use super::simulation::{KeyboardRouting, Simulation};
+
+/// Written to the user data directory by the quick-save hotkey
+pub const QUICKSAVE: &str = "quicksave.ron";
+
+#[derive(thiserror::Error, Debug)]
+pub enum SessionError {
+    #[error("Could not parse the session: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+
+    #[error("Could not write the session: {0}")]
+    Serialize(#[from] ron::Error),
+
+    #[error("Could not load the world: {0}")]
+    World(#[from] RegistryError),
+
+    #[error("Could not restore the firmware of [{key}]: {error}")]
+    Firmware {
+        key: WorldKey,
+        error: ExecutionError,
+    },
+}
+
+/// A running simulation written to disk, to be resumed exactly where it was saved:
+/// physics, entities down to their booster levels, and each firmware with its globals.
+/// Its entities are read and resumed with the registry of the running world, so kinds
+/// registered by other crates come back too.
+#[derive(Debug, Serialize)]
+pub struct Session {
+    tick: u64,
+    world: SavedWorld,
+    programmables: Vec<WorldKey>,
+    selected: Option<WorldKey>,
+    routing: KeyboardRouting,
+    firmware: HashMap<WorldKey, PathBuf>,
+    #[serde(default)]
+    prototypes: HashMap<String, Satellite>,
+    programs: HashMap<WorldKey, ProgramSnapshot>,
+}
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "lowercase")]
+enum SessionField {
+    Tick,
+    World,
+    Programmables,
+    Selected,
+    Routing,
+    Firmware,
+    Prototypes,
+    Programs,
+}
+
+/// Reads a `Session` whose world holds kinds of `.0`
+#[derive(Clone, Copy)]
+struct SessionSeed<'a>(&'a EntityRegistry);
+
+impl Session {
+    pub fn capture(simulation: &Simulation) -> Result<Self, SessionError> {
+        Ok(Self {
+            tick: simulation.tick,
+            world: simulation.world.save(),
+            programmables: simulation.programmables.clone(),
+            selected: simulation.selected,
+            routing: simulation.routing,
+            firmware: simulation.firmware.clone(),
+            prototypes: simulation.prototypes.clone(),
+            programs: simulation
+                .executors
+                .iter()
+                .map(|(key, lua)| (*key, lua.snapshot()))
+                .collect(),
+        })
+    }
+
+    pub fn resume(&self, registry: &EntityRegistry) -> Result<Simulation, SessionError> {
+        let mut simulation = Simulation::empty();
+        *simulation.world.registry_mut() = registry.clone();
+        simulation.world.load(&self.world)?;
+        simulation.tick = self.tick;
+        simulation.programmables = self.programmables.clone();
+        simulation.selected = self.selected;
+        simulation.routing = self.routing;
+        simulation.firmware = self.firmware.clone();
+        simulation.prototypes = self.prototypes.clone();
+
+        for key in &self.programmables {
+            let mut lua = LuaProgramExecutor::new();
+            if let Some(program) = self.programs.get(key) {
+                lua.restore(program)
+                    .map_err(|error| SessionError::Firmware { key: *key, error })?;
+            }
+
+            simulation.executors.insert(*key, lua);
+        }
+
+        Ok(simulation)
+    }
+
+    pub fn parse(source: &str, registry: &EntityRegistry) -> Result<Self, SessionError> {
+        Ok(ron::Options::default().from_str_seed(source, SessionSeed(registry))?)
+    }
+
+    pub fn to_ron(&self) -> Result<String, SessionError> {
+        Ok(ron::ser::to_string_pretty(self, PrettyConfig::default())?)
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for SessionSeed<'_> {
+    type Value = Session;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_struct(
+            "Session",
+            &[
+                "tick",
+                "world",
+                "programmables",
+                "selected",
+                "routing",
+                "firmware",
+                "prototypes",
+                "programs",
+            ],
+            self,
+        )
+    }
+}
+
+impl<'de> Visitor<'de> for SessionSeed<'_> {
+    type Value = Session;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a session")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut tick = None;
+        let mut world = None;
+        let mut programmables = None;
+        let mut selected = None;
+        let mut routing = None;
+        let mut firmware = None;
+        let mut prototypes = None;
+        let mut programs = None;
+
+        while let Some(field) = map.next_key()? {
+            match field {
+                SessionField::Tick => tick = Some(map.next_value()?),
+                SessionField::World => world = Some(map.next_value_seed(SavedWorldSeed(self.0))?),
+                SessionField::Programmables => programmables = Some(map.next_value()?),
+                SessionField::Selected => selected = Some(map.next_value()?),
+                SessionField::Routing => routing = Some(map.next_value()?),
+                SessionField::Firmware => firmware = Some(map.next_value()?),
+                SessionField::Prototypes => prototypes = Some(map.next_value()?),
+                SessionField::Programs => programs = Some(map.next_value()?),
+            }
+        }
+
+        Ok(Session {
+            tick: tick.ok_or_else(|| de::Error::missing_field("tick"))?,
+            world: world.ok_or_else(|| de::Error::missing_field("world"))?,
+            programmables: programmables
+                .ok_or_else(|| de::Error::missing_field("programmables"))?,
+            selected: selected.unwrap_or_default(),
+            routing: routing.ok_or_else(|| de::Error::missing_field("routing"))?,
+            firmware: firmware.ok_or_else(|| de::Error::missing_field("firmware"))?,
+            prototypes: prototypes.unwrap_or_default(),
+            programs: programs.ok_or_else(|| de::Error::missing_field("programs"))?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::design::SatelliteDesign;
+    use crate::lang::snapshot::LuaValueSnapshot;
+    use crate::system::lang_env::Unrouted;
+
+    fn global(snapshot: &ProgramSnapshot, name: &str) -> Option<LuaValueSnapshot> {
+        snapshot
+            .globals
+            .iter()
+            .find(|(global, _)| global == name)
+            .map(|(_, value)| value.clone())
+    }
+
+    /// Panics unless the satellite and its firmware are in the same state in both
+    fn assert_same_state(original: &Simulation, resumed: &Simulation, key: &WorldKey) {
+        assert_eq!(resumed.tick, original.tick);
+
+        let (saved, loaded) = (
+            original.satellite(key).unwrap(),
+            resumed.satellite(key).unwrap(),
+        );
+        assert_eq!(loaded.transform.location, saved.transform.location);
+        assert_eq!(loaded.transform.angle, saved.transform.angle);
+        assert_eq!(loaded.motion.linear, saved.motion.linear);
+        assert_eq!(loaded.motion.angular, saved.motion.angular);
+        assert_eq!(
+            loaded.booster["main"].thrust(),
+            saved.booster["main"].thrust()
+        );
+
+        let (saved, loaded) = (
+            original.executors[key].snapshot(),
+            resumed.executors[key].snapshot(),
+        );
+        assert!(global(&saved, "count").is_some());
+        assert_eq!(global(&loaded, "count"), global(&saved, "count"));
+        assert_eq!(loaded.program, saved.program);
+    }
+
+    #[test]
+    fn session_should_resume_exactly_where_it_was_saved() {
+        let design = SatelliteDesign::sample(
+            r#"boosters: [
+                (name: "main", at: (0.0, 0.5), direction: (0.0, -1.0), thruster: (max_thrust: 20.0)),
+            ],"#,
+        );
+
+        // The booster level follows a global, so the trajectory depends on it
+        let mut simulation = Simulation::empty();
+        let key = simulation.add_programmable(Satellite::new(design));
+        simulation
+            .load_program(
+                &key,
+                "count = 0
+                function main()
+                    count = count + 1
+                    api.boost('main', (count % 4) / 4)
+                    return ''
+                end",
+            )
+            .unwrap();
+
+        for _ in 0..5 {
+            assert!(simulation.step(&Unrouted).is_empty());
+        }
+        assert!(simulation.satellite(&key).unwrap().booster["main"].thrust() > 0.0);
+
+        let source = Session::capture(&simulation).unwrap().to_ron().unwrap();
+        let registry = simulation.world.registry();
+        let mut resumed = Session::parse(&source, registry)
+            .unwrap()
+            .resume(registry)
+            .unwrap();
+        assert_same_state(&simulation, &resumed, &key);
+
+        for _ in 0..30 {
+            assert!(simulation.step(&Unrouted).is_empty());
+            assert!(resumed.step(&Unrouted).is_empty());
+            assert_same_state(&simulation, &resumed, &key);
+        }
+    }
+}
@@ -0,0 +1,826 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::entity::docking::{can_latch, Dock, DockingPort, PlacedPort, ALIGNMENT_RANGE};
+use crate::entity::radio::{RadioMessage, RadioSpec};
+use crate::entity::satellite::{Satellite, PROXIMITY_RANGE};
+use crate::entity::waypoint::Waypoint;
+use crate::entity::{Capabilities, Entity};
+use crate::lang::exec::{ExecutionError, LuaProgramExecutor, ProgramSnapshot};
+use crate::lang::{ProgramEnvironment, Telemetry};
+use crate::world::{World, WorldKey};
+
+use super::lang_env::Unrouted;
+use super::snapshot::Snapshot;
+
+/// Which programmable entities read the keyboard through `api.is_pressed`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyboardRouting {
+    #[default]
+    Selected,
+    All,
+    Nobody,
+}
+
+pub struct Simulation {
+    pub world: World,
+    /// Every programmable entity, satellites and crafts alike, in the order they were added
+    pub programmables: Vec<WorldKey>,
+    pub executors: HashMap<WorldKey, LuaProgramExecutor>,
+    /// Where the firmware of each entity was loaded from, as reached from the working
+    /// directory, to be written into scenes
+    pub firmware: HashMap<WorldKey, PathBuf>,
+    /// Satellites deployed as payloads are cloned from these, by design name
+    pub prototypes: HashMap<String, Satellite>,
+    pub selected: Option<WorldKey>,
+    pub routing: KeyboardRouting,
+    pub tick: u64,
+}
+
+impl Simulation {
+    pub fn empty() -> Self {
+        Self {
+            world: World::default(),
+            programmables: Vec::new(),
+            executors: HashMap::new(),
+            firmware: HashMap::new(),
+            prototypes: HashMap::new(),
+            selected: None,
+            routing: KeyboardRouting::default(),
+            tick: 0,
+        }
+    }
+
+    /// Adds an entity that runs firmware, with an executor of its own.
+    pub fn add_programmable(&mut self, entity: impl Entity + 'static) -> WorldKey {
+        let (key, value) = self.world.insert(entity);
+        debug_assert!(
+            value
+                .entity
+                .capabilities()
+                .contains(Capabilities::PROGRAMMABLE),
+            "Entity kind '{}' is not programmable",
+            value.entity.kind()
+        );
+
+        self.programmables.push(key);
+        self.executors.insert(key, LuaProgramExecutor::new());
+
+        key
+    }
+
+    pub fn satellite(&self, key: &WorldKey) -> Option<&Satellite> {
+        self.world.get(key)?.entity.downcast_ref()
+    }
+
+    fn satellite_mut(&mut self, key: &WorldKey) -> Option<&mut Satellite> {
+        self.world.get_mut(key)?.entity.downcast_mut()
+    }
+
+    /// Where a programmable entity is and how it moves, whatever its kind
+    pub fn telemetry(&mut self, key: &WorldKey) -> Option<Telemetry> {
+        Some(self.world.get_mut(key)?.entity.as_client()?.telemetry())
+    }
+
+    pub fn load_program(&mut self, key: &WorldKey, program: &str) -> Result<(), ExecutionError> {
+        self.executors
+            .entry(*key)
+            .or_insert_with(LuaProgramExecutor::new)
+            .load(program)
+    }
+
+    pub fn load_firmware(
+        &mut self,
+        key: &WorldKey,
+        path: &Path,
+        program: &str,
+    ) -> Result<(), ExecutionError> {
+        self.load_program(key, program)?;
+        self.firmware.insert(*key, path.to_path_buf());
+
+        Ok(())
+    }
+
+    fn receives_keyboard(&self, key: &WorldKey) -> bool {
+        match self.routing {
+            KeyboardRouting::Selected => self.selected.as_ref() == Some(key),
+            KeyboardRouting::All => true,
+            KeyboardRouting::Nobody => false,
+        }
+    }
+
+    /// Runs the firmware of every programmable entity and advances the world by one tick.
+    /// Returns the firmware errors of this tick along with the entity that raised them.
+    pub fn step<E>(&mut self, env: &E) -> Vec<(WorldKey, ExecutionError)>
+    where
+        E: ProgramEnvironment + Send,
+    {
+        let mut errors: Vec<_> = self
+            .programmables
+            .clone()
+            .into_iter()
+            .filter_map(|key| {
+                let result = if self.receives_keyboard(&key) {
+                    self.update_lua(&key, env)
+                } else {
+                    self.update_lua(&key, &Unrouted)
+                };
+
+                result.err().map(|err| (key, err))
+            })
+            .collect();
+
+        errors.extend(self.spawn_deployments());
+        self.release_docks();
+        if let Err((key, err)) = self.world.update_all_entity() {
+            errors.push((key, ExecutionError::EnvironmentalError(err.to_string())));
+        }
+        self.sense_proximity();
+        self.latch_docks();
+        self.relay_radio();
+        self.trigger_waypoints();
+        self.tick += 1;
+
+        errors
+    }
+
+    fn update_lua<E>(&mut self, key: &WorldKey, env: &E) -> Result<(), ExecutionError>
+    where
+        E: ProgramEnvironment + Send,
+    {
+        let (Some(value), Some(lua)) = (self.world.get_mut(key), self.executors.get_mut(key))
+        else {
+            return Ok(());
+        };
+        let Some(client) = value.entity.as_client() else {
+            return Ok(());
+        };
+
+        lua.execute(client, env)
+    }
+
+    /// Spawns the payloads ejected by firmware this tick as satellites of their own
+    fn spawn_deployments(&mut self) -> Vec<(WorldKey, ExecutionError)> {
+        let mut errors = Vec::new();
+
+        for carrier in self.programmables.clone() {
+            let Some(satellite) = self.satellite_mut(&carrier) else {
+                continue;
+            };
+            if satellite.deployments.is_empty() {
+                continue;
+            }
+            let deployments = std::mem::take(&mut satellite.deployments);
+            let (transform, motion, design) = (
+                satellite.transform.clone(),
+                satellite.motion.clone(),
+                satellite.design.clone(),
+            );
+
+            for deployment in deployments {
+                let payload = design.payload(&deployment.payload).unwrap();
+                let Some(prototype) = self.prototypes.get(&payload.design) else {
+                    errors.push((
+                        carrier,
+                        ExecutionError::EnvironmentalError(format!(
+                            "Design ({}) of payload ({}) is not loaded",
+                            payload.design, payload.name
+                        )),
+                    ));
+                    continue;
+                };
+
+                let mut child = prototype.clone();
+                (child.transform, child.motion) =
+                    payload.eject(&transform, &motion, child.design.mass);
+
+                let key = self.add_programmable(child);
+                if let Some(program) = deployment.firmware {
+                    if let Err(error) = self.load_program(&key, &program) {
+                        errors.push((key, error));
+                    }
+                }
+            }
+        }
+
+        errors
+    }
+
+    /// Unlatches the ports the firmware asked to undock
+    fn release_docks(&mut self) {
+        for key in self.programmables.clone() {
+            let Some(satellite) = self.satellite_mut(&key) else {
+                continue;
+            };
+            if satellite.undocking.is_empty() {
+                continue;
+            }
+
+            let ports = std::mem::take(&mut satellite.undocking);
+            let (released, kept): (Vec<Dock>, Vec<Dock>) = satellite
+                .docks
+                .drain(..)
+                .partition(|dock| ports.contains(&dock.port));
+            satellite.docks = kept;
+
+            for dock in released {
+                self.world.release(&dock.joint);
+
+                if let Some(partner) = self.satellite_mut(&dock.with) {
+                    partner.docks.retain(|other| other.joint != dock.joint);
+                }
+            }
+        }
+    }
+
+    /// Latches the free ports of satellites that line up with a free port of another body,
+    /// and tells each satellite how its free ports line up.
+    fn latch_docks(&mut self) {
+        let mut latched = HashSet::new();
+        self.programmables
+            .iter()
+            .filter_map(|key| Some((*key, self.satellite(key)?)))
+            .for_each(|(key, satellite)| {
+                satellite.docks.iter().for_each(|dock| {
+                    latched.insert((key, dock.port.clone()));
+                    latched.insert((dock.with, dock.with_port.clone()));
+                });
+            });
+
+        let ports: Vec<(WorldKey, DockingPort, PlacedPort)> = self
+            .world
+            .iter_entity()
+            .filter_map(|entry| Some((entry.key, entry.value.entity.as_dockable()?)))
+            .flat_map(|(key, dockable)| {
+                let (transform, motion) = dockable.frame();
+
+                dockable
+                    .ports()
+                    .iter()
+                    .map(|port| (key, port.clone(), port.place(transform, motion)))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        for key in self.programmables.clone() {
+            let mut alignment = HashMap::new();
+
+            for (_, port, placed) in ports.iter().filter(|(owner, ..)| *owner == key) {
+                if latched.contains(&(key, port.name.clone())) {
+                    alignment.insert(port.name.clone(), None);
+                    continue;
+                }
+
+                let nearest = ports
+                    .iter()
+                    .filter(|(other, other_port, _)| {
+                        *other != key && !latched.contains(&(*other, other_port.name.clone()))
+                    })
+                    .map(|(other, other_port, other_placed)| {
+                        (other, other_port, placed.alignment(other_placed))
+                    })
+                    .filter(|(.., alignment)| alignment.distance <= ALIGNMENT_RANGE)
+                    .min_by(|a, b| a.2.distance.total_cmp(&b.2.distance));
+
+                let Some((other, other_port, reading)) = nearest else {
+                    alignment.insert(port.name.clone(), None);
+                    continue;
+                };
+                if !can_latch(&reading) {
+                    alignment.insert(port.name.clone(), Some(reading));
+                    continue;
+                }
+
+                let Some(joint) = self.world.latch((&key, port), (other, other_port)) else {
+                    continue;
+                };
+                latched.insert((key, port.name.clone()));
+                latched.insert((*other, other_port.name.clone()));
+                alignment.insert(port.name.clone(), None);
+
+                if let Some(satellite) = self.satellite_mut(&key) {
+                    satellite.docks.push(Dock {
+                        port: port.name.clone(),
+                        with: *other,
+                        with_port: other_port.name.clone(),
+                        joint,
+                    });
+                }
+                if let Some(partner) = self.satellite_mut(other) {
+                    partner.docks.push(Dock {
+                        port: other_port.name.clone(),
+                        with: key,
+                        with_port: port.name.clone(),
+                        joint,
+                    });
+                }
+            }
+
+            if let Some(satellite) = self.satellite_mut(&key) {
+                satellite.alignment = alignment;
+            }
+        }
+    }
+
+    /// Carries the messages sent this tick to every other radio within range of the sender
+    fn relay_radio(&mut self) {
+        let transmissions: Vec<(WorldKey, (f32, f32), RadioSpec, Vec<RadioMessage>)> = self
+            .programmables
+            .clone()
+            .into_iter()
+            .filter_map(|key| {
+                let satellite = self.satellite_mut(&key)?;
+                let location = satellite.transform.location;
+                let radio = satellite.radio.as_mut()?;
+
+                Some((key, location, radio.spec.clone(), radio.transmit()))
+            })
+            .collect();
+
+        for key in self.programmables.clone() {
+            let Some(satellite) = self.satellite_mut(&key) else {
+                continue;
+            };
+            let (x, y) = satellite.transform.location;
+            let Some(radio) = &mut satellite.radio else {
+                continue;
+            };
+
+            transmissions
+                .iter()
+                .filter(|(sender, (sx, sy), spec, _)| {
+                    *sender != key && (x - sx).hypot(y - sy) <= spec.range
+                })
+                .for_each(|(_, _, spec, messages)| {
+                    messages
+                        .iter()
+                        .for_each(|message| radio.deliver(message.clone(), spec.latency));
+                });
+
+            radio.tick();
+        }
+    }
+
+    fn sense_proximity(&mut self) {
+        let registry = self.world.registry();
+        let kinds: Vec<&'static str> = registry
+            .kinds_with(Capabilities::RIGID_BODY)
+            .chain(registry.kinds_with(Capabilities::ASSEMBLY))
+            .map(|kind| kind.name)
+            .collect();
+
+        self.programmables.clone().iter().for_each(|key| {
+            let Some(telemetry) = self.telemetry(key) else {
+                return;
+            };
+            let position = telemetry.position;
+
+            let proximity = kinds
+                .iter()
+                .map(|kind| {
+                    let nearest =
+                        self.world
+                            .nearest_of_kind(position, kind, PROXIMITY_RANGE, Some(key));
+
+                    (kind.to_string(), nearest.map(|(_, distance)| distance))
+                })
+                .collect();
+
+            if let Some(client) = self
+                .world
+                .get_mut(key)
+                .and_then(|value| value.entity.as_client())
+            {
+                client.report_proximity(proximity);
+            }
+        });
+    }
+
+    fn trigger_waypoints(&mut self) {
+        let waypoints: Vec<_> = self
+            .world
+            .iter_entity()
+            .filter_map(|entry| {
+                let waypoint = entry.value.entity.downcast_ref::<Waypoint>()?;
+                Some((entry.key, waypoint.location, waypoint.radius))
+            })
+            .collect();
+
+        waypoints.into_iter().for_each(|(key, location, radius)| {
+            let arrivals: Vec<WorldKey> = self
+                .world
+                .query_radius(location, radius)
+                .into_iter()
+                .filter(|candidate| self.programmables.contains(candidate))
+                .collect();
+
+            let Some(waypoint) = self
+                .world
+                .get_mut(&key)
+                .and_then(|value| value.entity.downcast_mut::<Waypoint>())
+            else {
+                return;
+            };
+
+            arrivals.into_iter().for_each(|arrival| {
+                if !waypoint.reached_by.contains(&arrival) {
+                    waypoint.reached_by.push(arrival);
+                }
+            });
+        });
+    }
+
+    pub fn capture(&self) -> Snapshot {
+        Snapshot {
+            tick: self.tick,
+            world: self.world.snapshot(),
+            programmables: self.programmables.clone(),
+            programs: self
+                .executors
+                .iter()
+                .map(|(key, lua)| (*key, lua.snapshot()))
+                .collect(),
+        }
+    }
+
+    pub fn restore(
+        &mut self,
+        snapshot: &Snapshot,
+        keep_program: bool,
+    ) -> Result<(), ExecutionError> {
+        self.world.restore(&snapshot.world);
+        self.tick = snapshot.tick;
+
+        // Forget the payloads deployed after the snapshot
+        self.programmables = snapshot.programmables.clone();
+        self.executors
+            .retain(|key, _| snapshot.programmables.contains(key));
+        self.firmware
+            .retain(|key, _| snapshot.programmables.contains(key));
+
+        self.executors
+            .iter_mut()
+            .try_for_each(|(key, lua)| match keep_program {
+                true => lua.restore(&ProgramSnapshot {
+                    program: lua.program().map(str::to_string),
+                    globals: Vec::new(),
+                }),
+                false => lua.restore(&snapshot.programs.get(key).cloned().unwrap_or_default()),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ggez::graphics::Color;
+    use ggez::{GameError, GameResult};
+
+    use super::*;
+    use crate::entity::design::SatelliteDesign;
+    use crate::entity::modular::ModularCraft;
+    use crate::entity::planet::Planet;
+    use crate::entity::station::Station;
+    use crate::entity::EntityKind;
+    use crate::lang::snapshot::LuaValueSnapshot;
+    use crate::lang::{ClientError, ModKey};
+    use crate::theory::geometry::Transform;
+
+    /// An entity whose update always fails
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    struct Jammed;
+
+    impl EntityKind for Jammed {
+        const NAME: &'static str = "jammed";
+        const CAPABILITIES: Capabilities = Capabilities::empty();
+    }
+
+    impl Entity for Jammed {
+        fn update(&mut self) -> GameResult {
+            Err(GameError::CustomError("jammed".to_string()))
+        }
+    }
+
+    /// A keyboard with every key held down
+    struct Pressing;
+
+    impl ProgramEnvironment for Pressing {
+        fn is_pressed(&self, _char: &str, _mods: Option<ModKey>) -> Result<bool, ClientError> {
+            Ok(true)
+        }
+    }
+
+    fn radio_satellite(location: (f32, f32)) -> Satellite {
+        let mut satellite = Satellite::new(SatelliteDesign::sample(
+            "boosters: [], radio: Some((range: 10.0, latency: 0, bandwidth: 256)),",
+        ));
+        satellite.transform = Transform::new(location, 0.0);
+        satellite
+    }
+
+    #[test]
+    fn firmware_should_boost_a_part_of_a_craft() {
+        let mut simulation = Simulation::empty();
+        let key = simulation.add_programmable(ModularCraft::sample(Transform::default()));
+        simulation
+            .load_program(
+                &key,
+                "function main() api.boost('wing_l', 1.0); return '' end",
+            )
+            .unwrap();
+
+        for _ in 0..10 {
+            assert!(simulation.step(&Unrouted).is_empty());
+        }
+
+        let craft = simulation.world.get(&key).unwrap().entity.as_ref();
+        let craft = craft.downcast_ref::<ModularCraft>().unwrap();
+        let wing = craft
+            .parts
+            .iter()
+            .find(|part| part.name == "wing_l")
+            .unwrap();
+
+        assert!(wing.boosters[0].thruster.thrust() > 0.0);
+        assert!(wing.motion.linear.1 < 0.0);
+    }
+
+    #[test]
+    fn craft_should_be_followed_and_sense_nearby_bodies() {
+        let mut simulation = Simulation::empty();
+        simulation
+            .world
+            .insert(Planet::new((20.0, 0.0), 5.0, Color::WHITE));
+        let key =
+            simulation.add_programmable(ModularCraft::sample(Transform::new((1.0, 2.0), 0.0)));
+
+        assert_eq!(simulation.telemetry(&key).unwrap().position, (1.0, 2.0));
+
+        assert!(simulation.step(&Unrouted).is_empty());
+        simulation
+            .load_program(
+                &key,
+                "function main() if api.nearest('planet') == nil then return 'blind' end; return '' end",
+            )
+            .unwrap();
+
+        assert!(simulation.step(&Unrouted).is_empty());
+    }
+
+    #[test]
+    fn failing_update_should_be_reported_by_the_step() {
+        let mut simulation = Simulation::empty();
+        simulation
+            .world
+            .registry_mut()
+            .register::<Jammed>()
+            .unwrap();
+        let key = simulation.world.insert(Jammed).0;
+
+        let errors = simulation.step(&Unrouted);
+        assert!(matches!(
+            errors.as_slice(),
+            [(failed, ExecutionError::EnvironmentalError(message))]
+                if *failed == key && message.contains("jammed")
+        ));
+        assert_eq!(simulation.tick, 1);
+    }
+
+    #[test]
+    fn waypoint_should_record_programmables_that_enter_it() {
+        let mut simulation = Simulation::empty();
+        let waypoint = simulation
+            .world
+            .insert(Waypoint::new("alpha", (0.0, 0.0), 2.0))
+            .0;
+        simulation
+            .world
+            .insert(Planet::new((0.0, 1.0), 0.5, Color::WHITE));
+        let inside = simulation.add_programmable(radio_satellite((1.0, 0.0)));
+        simulation.add_programmable(radio_satellite((10.0, 0.0)));
+
+        for _ in 0..2 {
+            assert!(simulation.step(&Unrouted).is_empty());
+        }
+
+        let waypoint = simulation.world.get(&waypoint).unwrap().entity.as_ref();
+        let waypoint = waypoint.downcast_ref::<Waypoint>().unwrap();
+        assert_eq!(waypoint.reached_by, vec![inside]);
+    }
+
+    #[test]
+    fn keyboard_should_reach_only_the_routed_programmables() {
+        let mut simulation = Simulation::empty();
+        let keys: Vec<_> = (0..3)
+            .map(|index| simulation.add_programmable(radio_satellite((index as f32 * 5.0, 0.0))))
+            .collect();
+        keys.iter().for_each(|key| {
+            simulation
+                .load_program(
+                    key,
+                    "function main() if api.is_pressed('w') then return 'pressed' end; return '' end",
+                )
+                .unwrap()
+        });
+        simulation.selected = Some(keys[1]);
+
+        let mut pressed = |routing| {
+            simulation.routing = routing;
+            simulation
+                .step(&Pressing)
+                .into_iter()
+                .map(|(key, _)| key)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(pressed(KeyboardRouting::Selected), vec![keys[1]]);
+        assert_eq!(pressed(KeyboardRouting::All), keys);
+        assert_eq!(pressed(KeyboardRouting::Nobody), vec![]);
+    }
+
+    #[test]
+    fn each_programmable_should_keep_its_own_globals() {
+        let program = "count = (count or 0) + 1; function main() count = count + 1; return '' end";
+        let mut simulation = Simulation::empty();
+        let first = simulation.add_programmable(radio_satellite((0.0, 0.0)));
+        let second = simulation.add_programmable(radio_satellite((5.0, 0.0)));
+
+        simulation.load_program(&first, program).unwrap();
+        for _ in 0..2 {
+            assert!(simulation.step(&Unrouted).is_empty());
+        }
+        simulation.load_program(&second, program).unwrap();
+        for _ in 0..3 {
+            assert!(simulation.step(&Unrouted).is_empty());
+        }
+
+        let count = |key: &WorldKey| {
+            simulation.executors[key]
+                .snapshot()
+                .globals
+                .into_iter()
+                .find(|(name, _)| name == "count")
+                .map(|(_, value)| value)
+        };
+
+        assert_eq!(count(&first), Some(LuaValueSnapshot::Integer(6)));
+        assert_eq!(count(&second), Some(LuaValueSnapshot::Integer(4)));
+    }
+
+    #[test]
+    fn radio_should_only_reach_satellites_in_range() {
+        let mut simulation = Simulation::empty();
+        let sender = simulation.add_programmable(radio_satellite((0.0, 0.0)));
+        let near = simulation.add_programmable(radio_satellite((5.0, 0.0)));
+        let far = simulation.add_programmable(radio_satellite((50.0, 0.0)));
+        simulation
+            .load_program(
+                &sender,
+                "function main() api.radio.send('ch', 'hello'); return '' end",
+            )
+            .unwrap();
+
+        for _ in 0..2 {
+            assert!(simulation.step(&Unrouted).is_empty());
+        }
+
+        let mut receive = |key: &WorldKey| {
+            simulation
+                .satellite_mut(key)
+                .unwrap()
+                .radio
+                .as_mut()
+                .unwrap()
+                .receive()
+        };
+
+        assert_eq!(
+            receive(&near),
+            Some(RadioMessage {
+                channel: "ch".to_string(),
+                message: "hello".to_string(),
+            })
+        );
+        assert_eq!(receive(&far), None);
+        assert_eq!(receive(&sender), None);
+    }
+
+    #[test]
+    fn deploying_from_an_empty_bay_should_fail_the_firmware() {
+        let mut simulation = Simulation::empty();
+        simulation.prototypes.insert(
+            "probe".to_string(),
+            Satellite::new(SatelliteDesign::sample("boosters: [],")),
+        );
+        let carrier = simulation.add_programmable(Satellite::new(SatelliteDesign::sample(
+            r#"boosters: [],
+            payloads: [
+                (name: "cubesat", design: "probe", at: (0.0, -0.5), direction: (0.0, -1.0), impulse: 1.0),
+            ],"#,
+        )));
+        simulation
+            .load_program(
+                &carrier,
+                "function main() api.deploy('cubesat'); return '' end",
+            )
+            .unwrap();
+
+        assert!(simulation.step(&Unrouted).is_empty());
+        assert_eq!(simulation.programmables.len(), 2);
+
+        let errors = simulation.step(&Unrouted);
+        assert_eq!(simulation.programmables.len(), 2);
+        assert!(matches!(
+            errors.as_slice(),
+            [(key, ExecutionError::DynamicError(message))]
+                if *key == carrier && message.contains("No cubesat left in the bay")
+        ));
+    }
+
+    #[test]
+    fn radio_on_a_craft_without_one_should_fail_the_firmware() {
+        let mut simulation = Simulation::empty();
+        let key = simulation.add_programmable(ModularCraft::sample(Transform::default()));
+        simulation
+            .load_program(
+                &key,
+                "function main() api.radio.send('ch', 'hello'); return '' end",
+            )
+            .unwrap();
+
+        let errors = simulation.step(&Unrouted);
+        assert!(matches!(
+            errors.as_slice(),
+            [(failed, ExecutionError::DynamicError(message))]
+                if *failed == key && message.contains("This client has no radio")
+        ));
+    }
+
+    #[test]
+    fn aligned_ports_should_latch_until_undocked() {
+        let mut simulation = Simulation::empty();
+        simulation.world.insert(Station::new(Transform::default()));
+
+        // Closing in on the port under the station slower than undocking pushes it away
+        let mut satellite = Satellite::new(SatelliteDesign::sample(
+            r#"boosters: [], ports: [(name: "top", at: (0.0, 0.5), direction: (0.0, 1.0))],"#,
+        ));
+        satellite.transform = Transform::new((0.0, -2.2), 0.0);
+        satellite.motion.linear = (0.0, 0.05);
+        let key = simulation.add_programmable(satellite);
+        simulation
+            .load_program(
+                &key,
+                "function main() if api.docked('top') then api.undock('top') end return '' end",
+            )
+            .unwrap();
+
+        assert!(simulation.step(&Unrouted).is_empty());
+        let docks = &simulation.satellite(&key).unwrap().docks;
+        assert_eq!(docks.len(), 1);
+        assert_eq!(docks[0].with_port, "main");
+        let joint = docks[0].joint;
+        assert!(simulation.world.is_latched(&joint));
+
+        assert!(simulation.step(&Unrouted).is_empty());
+        assert!(simulation.satellite(&key).unwrap().docks.is_empty());
+        assert!(!simulation.world.is_latched(&joint));
+    }
+
+    #[test]
+    fn sensing_an_unknown_kind_should_fail_the_firmware() {
+        let mut simulation = Simulation::empty();
+        let key =
+            simulation.add_programmable(Satellite::new(SatelliteDesign::sample("boosters: [],")));
+        simulation
+            .load_program(&key, "function main() api.nearest('comet'); return '' end")
+            .unwrap();
+
+        let errors = simulation.step(&Unrouted);
+        assert!(matches!(
+            errors.as_slice(),
+            [(failed, ExecutionError::DynamicError(message))]
+                if *failed == key && message.contains("Unknown kind (comet)")
+        ));
+    }
+
+    #[test]
+    fn wheel_torque_over_the_limit_should_fail_the_firmware() {
+        let mut simulation = Simulation::empty();
+        let key = simulation.add_programmable(Satellite::new(SatelliteDesign::sample(
+            "boosters: [], wheel: Some((max_torque: 1.0, max_momentum: 2.0, inertia: 0.1)),",
+        )));
+        simulation
+            .load_program(&key, "function main() api.wheel(2.0); return '' end")
+            .unwrap();
+
+        let errors = simulation.step(&Unrouted);
+        assert!(matches!(
+            errors.as_slice(),
+            [(failed, ExecutionError::DynamicError(message))]
+                if *failed == key && message.contains("torque should be within")
+        ));
+        assert_eq!(simulation.satellite(&key).unwrap().motion.angular, 0.0);
+    }
+}
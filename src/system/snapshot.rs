@@ -0,0 +1,56 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::lang::exec::ProgramSnapshot;
+use crate::world::{WorldKey, WorldSnapshot};
+
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    pub tick: u64,
+    pub world: WorldSnapshot,
+    pub programmables: Vec<WorldKey>,
+    pub programs: HashMap<WorldKey, ProgramSnapshot>,
+}
+
+#[derive(Debug)]
+pub struct SnapshotHistory {
+    snapshots: VecDeque<Snapshot>,
+    capacity: usize,
+    interval: u64,
+}
+
+impl SnapshotHistory {
+    pub fn new(capacity: usize, interval: u64) -> Self {
+        Self {
+            snapshots: VecDeque::with_capacity(capacity),
+            capacity,
+            interval,
+        }
+    }
+
+    pub fn should_capture(&self, tick: u64) -> bool {
+        tick % self.interval == 0
+    }
+
+    pub fn push(&mut self, snapshot: Snapshot) {
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+
+        self.snapshots.push_back(snapshot);
+    }
+
+    pub fn find(&self, tick: u64) -> Option<&Snapshot> {
+        self.snapshots.iter().find(|snapshot| snapshot.tick == tick)
+    }
+
+    pub fn forget_after(&mut self, tick: u64) {
+        self.snapshots.retain(|snapshot| snapshot.tick <= tick);
+    }
+
+    pub fn ticks(&self) -> Vec<u64> {
+        self.snapshots
+            .iter()
+            .map(|snapshot| snapshot.tick)
+            .collect()
+    }
+}
@@ -1,8 +1,60 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
 use ggez::{graphics, GameResult};
 
+use super::camera::Camera;
+use super::clock::SimulationClock;
+use super::simulation::KeyboardRouting;
+use super::viewport::Viewport;
+use crate::lang::Alignment;
+use crate::world::WorldKey;
+
 pub struct GameState {
-    pub satellite_svg: graphics::Image,
-    pub next_lua_program: Option<String>,
+    pub sprites: HashMap<String, graphics::Image>,
+    /// A filled white circle with a radius of 1 pixel, to be scaled and tinted on draw
+    pub circle: graphics::Mesh,
+    pub next_lua_program: Option<(WorldKey, PathBuf, String)>,
+    pub fleet: FleetState,
+    pub scene: FileRequests,
+    pub session: FileRequests,
+    pub rewind: RewindState,
+    pub clock: SimulationClock,
+    pub camera: Camera,
+    pub viewport: Viewport,
+    /// Health of each part of the selected satellite, from 0 (destroyed) to 1
+    pub health: Vec<(String, f32)>,
+    /// Docking status of each port of the selected satellite
+    pub docking: Vec<(String, PortStatus)>,
+}
+
+pub enum PortStatus {
+    Docked,
+    /// Free, with a free port of another body in range
+    Aligning(Alignment),
+    Free,
+}
+
+#[derive(Default)]
+pub struct RewindState {
+    pub available_ticks: Vec<u64>,
+    pub requested: Option<u64>,
+    pub keep_program: bool,
+}
+
+/// Files the GUI asked to load from or save to
+#[derive(Default)]
+pub struct FileRequests {
+    pub load: Option<PathBuf>,
+    pub save: Option<PathBuf>,
+    pub error: Option<String>,
+}
+
+#[derive(Default)]
+pub struct FleetState {
+    pub satellites: Vec<WorldKey>,
+    pub selected: Option<WorldKey>,
+    pub routing: KeyboardRouting,
 }
 
 #[derive(PartialEq, Eq)]
@@ -13,18 +65,46 @@ pub enum KeyPressTiming {
 }
 
 impl GameState {
-    pub fn new(ctx: &mut ggez::Context) -> GameResult<Self> {
-        let satellite_svg = graphics::Image::from_path(ctx, "/imgs/satellite.png")?;
+    pub fn new(ctx: &ggez::Context) -> GameResult<Self> {
+        let circle = graphics::Mesh::new_circle(
+            ctx,
+            graphics::DrawMode::fill(),
+            [0.0, 0.0],
+            1.0,
+            0.001,
+            graphics::Color::WHITE,
+        )?;
 
         Ok(Self {
-            satellite_svg,
+            sprites: HashMap::new(),
+            circle,
             next_lua_program: None,
+            fleet: FleetState::default(),
+            scene: FileRequests::default(),
+            session: FileRequests::default(),
+            rewind: RewindState::default(),
+            clock: SimulationClock::new(),
+            camera: Camera::new(),
+            viewport: Viewport::of_window(ctx),
+            health: Vec::new(),
+            docking: Vec::new(),
         })
     }
 
+    pub fn load_sprite(&mut self, ctx: &mut ggez::Context, path: &str) -> GameResult {
+        if !self.sprites.contains_key(path) {
+            let sprite = graphics::Image::from_path(ctx, format!("/{path}"))?;
+            self.sprites.insert(path.to_string(), sprite);
+        }
+
+        Ok(())
+    }
+
     pub fn tick_state(&mut self) {}
 
-    pub fn load_lua_program(&mut self, program: &str) {
-        self.next_lua_program = Some(program.to_string());
+    pub fn load_lua_program(&mut self, path: &Path, program: &str) {
+        if let Some(key) = self.fleet.selected {
+            self.next_lua_program = Some((key, path.to_path_buf(), program.to_string()));
+        }
     }
 }
@@ -0,0 +1,59 @@
+use ggez::glam::{vec2, Vec2};
+use ggez::graphics::Rect;
+use ggez::Context;
+
+/// The drawable area of the window. The world and the HUD are laid out in logical pixels,
+/// each of which covers `scale_factor` physical pixels on HiDPI screens.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    /// In physical pixels
+    pub size: Vec2,
+    pub scale_factor: f32,
+}
+
+impl Viewport {
+    pub fn new(size: Vec2, scale_factor: f32) -> Self {
+        Self { size, scale_factor }
+    }
+
+    /// Reads the current size and scale factor of the window.
+    pub fn of_window(ctx: &Context) -> Self {
+        let (width, height) = ctx.gfx.drawable_size();
+
+        Self::new(vec2(width, height), ctx.gfx.window().scale_factor() as f32)
+    }
+
+    pub fn logical_size(&self) -> Vec2 {
+        self.size / self.scale_factor
+    }
+
+    pub fn to_logical(&self, physical: Vec2) -> Vec2 {
+        physical / self.scale_factor
+    }
+
+    /// Screen coordinates of a canvas that draws in logical pixels
+    pub fn logical_rect(&self) -> Rect {
+        let size = self.logical_size();
+
+        Rect::new(0.0, 0.0, size.x, size.y)
+    }
+
+    /// Screen coordinates of a canvas that draws in physical pixels
+    pub fn physical_rect(&self) -> Rect {
+        Rect::new(0.0, 0.0, self.size.x, self.size.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn viewport_should_lay_out_in_logical_pixels() {
+        let viewport = Viewport::new(vec2(2560.0, 1440.0), 2.0);
+
+        assert_eq!(viewport.logical_size(), vec2(1280.0, 720.0));
+        assert_eq!(viewport.to_logical(vec2(200.0, 100.0)), vec2(100.0, 50.0));
+        assert_eq!(viewport.logical_rect(), Rect::new(0.0, 0.0, 1280.0, 720.0));
+    }
+}
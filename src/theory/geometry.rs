@@ -1,6 +1,7 @@
 use rapier2d::na::{Rotation2, Vector2};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct Transform {
     pub location: (f32, f32),
     pub angle: f32,
@@ -18,9 +19,69 @@ pub fn rotate_vec2(radian: f32, vector: (f32, f32)) -> (f32, f32) {
     (rotated.x, rotated.y)
 }
 
+pub fn convex_hull(points: &[(f32, f32)]) -> Vec<(f32, f32)> {
+    let mut points = points.to_vec();
+    points.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    points.dedup();
+
+    if points.len() < 3 {
+        return points;
+    }
+
+    fn cross(o: (f32, f32), a: (f32, f32), b: (f32, f32)) -> f32 {
+        (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+    }
+
+    fn half_hull<'a>(points: impl Iterator<Item = &'a (f32, f32)>) -> Vec<(f32, f32)> {
+        let mut chain: Vec<(f32, f32)> = Vec::new();
+
+        for point in points {
+            while chain.len() >= 2
+                && cross(chain[chain.len() - 2], chain[chain.len() - 1], *point) <= 0.0
+            {
+                chain.pop();
+            }
+            chain.push(*point);
+        }
+
+        chain.pop();
+        chain
+    }
+
+    let mut hull = half_hull(points.iter());
+    hull.extend(half_hull(points.iter().rev()));
+    hull
+}
+
+pub fn trace_alpha_hull(
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    alpha_threshold: u8,
+) -> Vec<(f32, f32)> {
+    let opaque = |x: u32, y: u32| rgba[((y * width + x) * 4 + 3) as usize] > alpha_threshold;
+
+    let edges: Vec<(f32, f32)> = (0..height)
+        .filter_map(|y| {
+            let left = (0..width).find(|x| opaque(*x, y))?;
+            let right = (0..width).rev().find(|x| opaque(*x, y))?;
+
+            Some([
+                (left as f32, y as f32),
+                (left as f32, (y + 1) as f32),
+                ((right + 1) as f32, y as f32),
+                ((right + 1) as f32, (y + 1) as f32),
+            ])
+        })
+        .flatten()
+        .collect();
+
+    convex_hull(&edges)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::theory::geometry::rotate_vec2;
+    use crate::theory::geometry::{convex_hull, rotate_vec2, trace_alpha_hull};
     use std::f32::consts;
 
     #[test]
@@ -30,6 +91,24 @@ mod tests {
         assert_approx_eq(rotate_vec2(-consts::FRAC_PI_2, (0.0, 1.0)), (1.0, 0.0));
     }
 
+    #[test]
+    fn convex_hull_should_drop_inner_points() {
+        let hull = convex_hull(&[(0.0, 0.0), (2.0, 0.0), (1.0, 1.0), (2.0, 2.0), (0.0, 2.0)]);
+
+        assert_eq!(hull, vec![(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)]);
+    }
+
+    #[test]
+    fn trace_alpha_hull_should_wrap_opaque_pixels() {
+        // 3x3 image where only the center pixel is opaque
+        let mut rgba = vec![0; 3 * 3 * 4];
+        rgba[(3 + 1) * 4 + 3] = 255;
+
+        let hull = trace_alpha_hull(&rgba, 3, 3, 0);
+
+        assert_eq!(hull, vec![(1.0, 1.0), (2.0, 1.0), (2.0, 2.0), (1.0, 2.0)]);
+    }
+
     fn assert_approx_eq(left: (f32, f32), right: (f32, f32)) {
         if left == right {
             return;
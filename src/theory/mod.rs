@@ -1,2 +1,3 @@
 pub mod geometry;
 pub mod physics;
+pub mod units;
@@ -1,20 +1,204 @@
 use super::geometry::Transform;
+use image::ImageResult;
 use rapier2d::na::Vector2;
 use rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::theory::geometry::rotate_vec2;
+use crate::system::clock::TICKS_PER_SECOND;
+use crate::theory::geometry::{rotate_vec2, trace_alpha_hull};
 use std::fmt::{Debug, Formatter};
+use std::sync::Mutex;
+
+/// Length of one physics step, in seconds; the clock runs one step per tick
+pub const TIMESTEP: f32 = 1.0 / TICKS_PER_SECOND as f32;
+
+/// Contacts pushing softer than this, in newtons, are not reported
+const CONTACT_FORCE_THRESHOLD: f32 = 1000.0;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum ColliderShape {
+    Cuboid { width: f32, height: f32 },
+    Ball { radius: f32 },
+    Capsule { half_height: f32, radius: f32 },
+    ConvexPolygon(Vec<(f32, f32)>),
+    Compound(Vec<(Transform, ColliderShape)>),
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Motion {
+    pub linear: (f32, f32),
+    pub angular: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BodyKind {
+    Dynamic,
+    /// Never moves, whatever hits it
+    Fixed,
+}
 
 #[derive(Debug)]
 pub struct RigidBodyProperty {
+    pub kind: BodyKind,
     pub mass: f32,
-    pub size: (f32, f32),
+    pub shape: ColliderShape,
     pub initial_transform: Transform,
+    pub initial_motion: Motion,
 }
 
-#[derive(Debug)]
+impl ColliderShape {
+    pub fn traced(rgba: &[u8], width: u32, height: u32, scale: f32, alpha_threshold: u8) -> Self {
+        let hull = trace_alpha_hull(rgba, width, height, alpha_threshold)
+            .into_iter()
+            .map(|(x, y)| {
+                (
+                    (x - width as f32 / 2.0) * scale,
+                    (y - height as f32 / 2.0) * scale,
+                )
+            })
+            .collect();
+
+        ColliderShape::ConvexPolygon(hull)
+    }
+
+    pub fn from_sprite(bytes: &[u8], scale: f32, alpha_threshold: u8) -> ImageResult<Self> {
+        let sprite = image::load_from_memory(bytes)?.to_rgba8();
+
+        Ok(Self::traced(
+            sprite.as_raw(),
+            sprite.width(),
+            sprite.height(),
+            scale,
+            alpha_threshold,
+        ))
+    }
+
+    pub fn to_shared_shape(&self) -> SharedShape {
+        match self {
+            ColliderShape::Compound(_) => {
+                let mut shapes = Vec::new();
+                self.flatten(Isometry::identity(), &mut shapes);
+
+                SharedShape::compound(shapes)
+            }
+            _ => self.to_primitive_shape(),
+        }
+    }
+
+    fn to_primitive_shape(&self) -> SharedShape {
+        match self {
+            ColliderShape::Cuboid { width, height } => {
+                SharedShape::cuboid(width / 2.0, height / 2.0)
+            }
+            ColliderShape::Ball { radius } => SharedShape::ball(*radius),
+            ColliderShape::Capsule {
+                half_height,
+                radius,
+            } => SharedShape::capsule_y(*half_height, *radius),
+            ColliderShape::ConvexPolygon(vertices) => {
+                let points: Vec<Point<Real>> =
+                    vertices.iter().copied().map(tuple_to_point).collect();
+
+                // Degenerate hulls (e.g. a fully transparent sprite) still need some body
+                SharedShape::convex_hull(&points).unwrap_or_else(|| {
+                    let radius = vertices
+                        .iter()
+                        .map(|(x, y)| x.hypot(*y))
+                        .fold(1.0, f32::max);
+
+                    SharedShape::ball(radius)
+                })
+            }
+            ColliderShape::Compound(_) => unreachable!("compound shapes are flattened"),
+        }
+    }
+
+    fn flatten(&self, at: Isometry<Real>, shapes: &mut Vec<(Isometry<Real>, SharedShape)>) {
+        match self {
+            ColliderShape::Compound(children) => {
+                children.iter().for_each(|(transform, child)| {
+                    let local = Isometry::new(tuple_to_vec(transform.location), transform.angle);
+                    child.flatten(at * local, shapes);
+                });
+            }
+            _ => shapes.push((at, self.to_primitive_shape())),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum JointKind {
+    Fixed,
+    Revolute { limits: Option<(f32, f32)> },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JointProperty {
+    pub parts: (usize, usize),
+    pub anchors: ((f32, f32), (f32, f32)),
+    pub kind: JointKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Physics(RigidBodyHandle);
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Joint(ImpulseJointHandle);
+
+/// A contact observed during the last tick; `point` is in world space and `impulse` in
+/// newton-seconds.
+#[derive(Clone, Debug)]
+pub struct Contact {
+    pub body: Physics,
+    pub point: (f32, f32),
+    pub impulse: f32,
+}
+
+#[derive(Default)]
+struct ContactCollector(Mutex<Vec<Contact>>);
+
+impl EventHandler for ContactCollector {
+    fn handle_collision_event(
+        &self,
+        _bodies: &RigidBodySet,
+        _colliders: &ColliderSet,
+        _event: CollisionEvent,
+        _contact_pair: Option<&ContactPair>,
+    ) {
+    }
+
+    fn handle_contact_force_event(
+        &self,
+        dt: Real,
+        _bodies: &RigidBodySet,
+        colliders: &ColliderSet,
+        contact_pair: &ContactPair,
+        total_force_magnitude: Real,
+    ) {
+        let Some(point) = contact_pair
+            .manifolds
+            .iter()
+            .flat_map(|manifold| manifold.data.solver_contacts.iter())
+            .map(|contact| contact.point)
+            .next()
+        else {
+            return;
+        };
+
+        let mut contacts = self.0.lock().unwrap();
+        [contact_pair.collider1, contact_pair.collider2]
+            .into_iter()
+            .filter_map(|collider| colliders.get(collider)?.parent())
+            .for_each(|body| {
+                contacts.push(Contact {
+                    body: Physics(body),
+                    point: (point.x, point.y),
+                    impulse: total_force_magnitude * dt,
+                })
+            });
+    }
+}
+
 #[derive(Debug)]
 pub struct PhysicsController<'a>(pub &'a mut RigidBody);
 
@@ -39,12 +223,41 @@ impl<'a> PhysicsController<'a> {
         self.apply_force(Some(at), vector);
     }
 
+    pub fn apply_impulse_locally(&mut self, at: (f32, f32), impulse: (f32, f32)) {
+        let angle = self.0.rotation().angle();
+
+        let at = rotate_vec2(angle, at);
+        let impulse = rotate_vec2(angle, impulse);
+
+        self.0.apply_impulse_at_point(
+            tuple_to_vec(impulse),
+            (tuple_to_vec(at) + self.0.position().translation.vector).into(),
+            true,
+        );
+    }
+
+    pub fn apply_torque(&mut self, torque: f32) {
+        self.0.add_torque(torque, true);
+    }
+
+    pub fn reset(&mut self) {
+        self.0.reset_forces(true);
+        self.0.reset_torques(true);
+    }
+
     pub fn to_transform(&self) -> Transform {
         Transform {
             location: (self.0.translation().x, self.0.translation().y),
             angle: self.0.rotation().angle(),
         }
     }
+
+    pub fn to_motion(&self) -> Motion {
+        Motion {
+            linear: (self.0.linvel().x, self.0.linvel().y),
+            angular: self.0.angvel(),
+        }
+    }
 }
 
 pub struct PhysicalWorld {
@@ -59,6 +272,19 @@ pub struct PhysicalWorld {
     impulse_joint_set: ImpulseJointSet,
     multibody_joint_set: MultibodyJointSet,
     ccd_solver: CCDSolver,
+    query_pipeline: QueryPipeline,
+    contacts: Vec<Contact>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PhysicsSnapshot {
+    rigidbody_set: RigidBodySet,
+    island_manager: IslandManager,
+    broad_phase: BroadPhase,
+    narrow_phase: NarrowPhase,
+    collider_set: ColliderSet,
+    impulse_joint_set: ImpulseJointSet,
+    multibody_joint_set: MultibodyJointSet,
 }
 
 impl Default for PhysicalWorld {
@@ -73,13 +299,22 @@ impl Debug for PhysicalWorld {
     }
 }
 
+impl Debug for PhysicsSnapshot {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<PhysicsSnapshot: {}>", self.rigidbody_set.len())
+    }
+}
+
 impl PhysicalWorld {
     pub fn new() -> Self {
         PhysicalWorld {
             rigidbody_set: RigidBodySet::default(),
             physics_pipeline: PhysicsPipeline::new(),
             gravity: vector![0.0, 0.0],
-            integration_parameters: IntegrationParameters::default(),
+            integration_parameters: IntegrationParameters {
+                dt: TIMESTEP,
+                ..Default::default()
+            },
             island_manager: IslandManager::new(),
             broad_phase: BroadPhase::new(),
             narrow_phase: NarrowPhase::new(),
@@ -87,18 +322,31 @@ impl PhysicalWorld {
             impulse_joint_set: ImpulseJointSet::new(),
             multibody_joint_set: MultibodyJointSet::new(),
             ccd_solver: CCDSolver::new(),
+            query_pipeline: QueryPipeline::new(),
+            contacts: Vec::new(),
         }
     }
 
-    pub fn register(&mut self, property: RigidBodyProperty) -> Physics {
-        let rigidbody = RigidBodyBuilder::dynamic()
+    /// `tag` is stored on the colliders and is what the spatial queries report back.
+    pub fn register(&mut self, property: RigidBodyProperty, tag: u128) -> Physics {
+        let builder = match property.kind {
+            BodyKind::Dynamic => RigidBodyBuilder::dynamic(),
+            BodyKind::Fixed => RigidBodyBuilder::fixed(),
+        };
+
+        let rigidbody = builder
             .translation(tuple_to_vec(property.initial_transform.location))
             .rotation(property.initial_transform.angle)
-            .additional_mass(property.mass)
+            .linvel(tuple_to_vec(property.initial_motion.linear))
+            .angvel(property.initial_motion.angular)
             .build();
 
-        let collider =
-            ColliderBuilder::cuboid(property.size.0 / 2.0, property.size.1 / 2.0).build();
+        let collider = ColliderBuilder::new(property.shape.to_shared_shape())
+            .mass(property.mass)
+            .active_events(ActiveEvents::CONTACT_FORCE_EVENTS)
+            .contact_force_event_threshold(CONTACT_FORCE_THRESHOLD)
+            .user_data(tag)
+            .build();
 
         let handle = self.rigidbody_set.insert(rigidbody);
 
@@ -108,7 +356,108 @@ impl PhysicalWorld {
         Physics(handle)
     }
 
+    pub fn register_assembly(
+        &mut self,
+        parts: Vec<RigidBodyProperty>,
+        joints: Vec<JointProperty>,
+        tag: u128,
+    ) -> Vec<Physics> {
+        let physics: Vec<Physics> = parts
+            .into_iter()
+            .map(|property| self.register(property, tag))
+            .collect();
+
+        for joint in joints {
+            let (anchor1, anchor2) = (
+                tuple_to_point(joint.anchors.0),
+                tuple_to_point(joint.anchors.1),
+            );
+
+            let data: GenericJoint = match joint.kind {
+                JointKind::Fixed => FixedJointBuilder::new()
+                    .local_anchor1(anchor1)
+                    .local_anchor2(anchor2)
+                    .contacts_enabled(false)
+                    .build()
+                    .into(),
+                JointKind::Revolute { limits } => {
+                    let mut builder = RevoluteJointBuilder::new()
+                        .local_anchor1(anchor1)
+                        .local_anchor2(anchor2)
+                        .contacts_enabled(false);
+
+                    if let Some((min, max)) = limits {
+                        builder = builder.limits([min, max]);
+                    }
+
+                    builder.build().into()
+                }
+            };
+
+            self.impulse_joint_set.insert(
+                physics[joint.parts.0].0,
+                physics[joint.parts.1].0,
+                data,
+                true,
+            );
+        }
+
+        physics
+    }
+
+    /// Locks two bodies together so that `frames.0` on the first and `frames.1` on the
+    /// second, both in their body's local space, stay on top of each other.
+    pub fn latch(&mut self, bodies: (&Physics, &Physics), frames: (Transform, Transform)) -> Joint {
+        let isometry = |frame: Transform| Isometry::new(tuple_to_vec(frame.location), frame.angle);
+
+        let data = FixedJointBuilder::new()
+            .local_frame1(isometry(frames.0))
+            .local_frame2(isometry(frames.1))
+            .contacts_enabled(false)
+            .build();
+
+        Joint(
+            self.impulse_joint_set
+                .insert(bodies.0 .0, bodies.1 .0, data, true),
+        )
+    }
+
+    pub fn release(&mut self, joint: &Joint) -> bool {
+        self.impulse_joint_set.remove(joint.0, true).is_some()
+    }
+
+    pub fn is_latched(&self, joint: &Joint) -> bool {
+        self.impulse_joint_set.get(joint.0).is_some()
+    }
+
+    pub fn contains(&self, physics: &Physics) -> bool {
+        self.rigidbody_set.contains(physics.0)
+    }
+
+    pub fn body_count(&self) -> usize {
+        self.rigidbody_set.len()
+    }
+
+    pub fn collider_count(&self) -> usize {
+        self.collider_set.len()
+    }
+
+    pub fn unregister(&mut self, physics: &Physics) -> bool {
+        self.rigidbody_set
+            .remove(
+                physics.0,
+                &mut self.island_manager,
+                &mut self.collider_set,
+                &mut self.impulse_joint_set,
+                &mut self.multibody_joint_set,
+                true,
+            )
+            .is_some()
+    }
+
     pub fn tick(&mut self) {
+        let collector = ContactCollector::default();
+
         self.physics_pipeline.step(
             &self.gravity,
             &self.integration_parameters,
@@ -120,10 +469,133 @@ impl PhysicalWorld {
             &mut self.impulse_joint_set,
             &mut self.multibody_joint_set,
             &mut self.ccd_solver,
-            None,
-            &(),
+            Some(&mut self.query_pipeline),
             &(),
+            &collector,
+        );
+
+        self.contacts = collector.0.into_inner().unwrap();
+    }
+
+    pub fn contacts(&self) -> &[Contact] {
+        &self.contacts
+    }
+
+    pub fn snapshot(&self) -> PhysicsSnapshot {
+        PhysicsSnapshot {
+            rigidbody_set: self.rigidbody_set.clone(),
+            island_manager: self.island_manager.clone(),
+            broad_phase: self.broad_phase.clone(),
+            narrow_phase: self.narrow_phase.clone(),
+            collider_set: self.collider_set.clone(),
+            impulse_joint_set: self.impulse_joint_set.clone(),
+            multibody_joint_set: self.multibody_joint_set.clone(),
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: &PhysicsSnapshot) {
+        let snapshot = snapshot.clone();
+
+        self.rigidbody_set = snapshot.rigidbody_set;
+        self.island_manager = snapshot.island_manager;
+        self.broad_phase = snapshot.broad_phase;
+        self.narrow_phase = snapshot.narrow_phase;
+        self.collider_set = snapshot.collider_set;
+        self.impulse_joint_set = snapshot.impulse_joint_set;
+        self.multibody_joint_set = snapshot.multibody_joint_set;
+
+        // The pipelines and the CCD solver only hold caches, which are rebuilt on the next step
+        self.physics_pipeline = PhysicsPipeline::new();
+        self.ccd_solver = CCDSolver::new();
+        self.query_pipeline = QueryPipeline::new();
+        self.contacts.clear();
+    }
+
+    pub fn cast_ray(
+        &self,
+        origin: (f32, f32),
+        direction: (f32, f32),
+        max_range: f32,
+        exclude: &Physics,
+    ) -> Option<f32> {
+        // A ray without a direction hits nothing
+        let direction = tuple_to_vec(direction).try_normalize(Real::EPSILON)?;
+        let ray = Ray::new(tuple_to_point(origin), direction);
+        let filter = QueryFilter::default().exclude_rigid_body(exclude.0);
+
+        self.query_pipeline
+            .cast_ray(
+                &self.rigidbody_set,
+                &self.collider_set,
+                &ray,
+                max_range,
+                true,
+                filter,
+            )
+            .map(|(_, distance)| distance)
+    }
+
+    /// Tags of the colliders overlapping the circle, as of the last tick.
+    pub fn intersect_ball(&self, center: (f32, f32), radius: f32) -> Vec<u128> {
+        self.intersect_shape(
+            Isometry::translation(center.0, center.1),
+            &Ball::new(radius),
+        )
+    }
+
+    /// Tags of the colliders overlapping the axis-aligned rectangle, as of the last tick.
+    pub fn intersect_rect(&self, min: (f32, f32), max: (f32, f32)) -> Vec<u128> {
+        self.intersect_shape(
+            Isometry::translation((min.0 + max.0) / 2.0, (min.1 + max.1) / 2.0),
+            &Cuboid::new(vector![
+                (max.0 - min.0).abs() / 2.0,
+                (max.1 - min.1).abs() / 2.0
+            ]),
+        )
+    }
+
+    fn intersect_shape(&self, at: Isometry<Real>, shape: &dyn Shape) -> Vec<u128> {
+        let mut tags = Vec::new();
+
+        self.query_pipeline.intersections_with_shape(
+            &self.rigidbody_set,
+            &self.collider_set,
+            &at,
+            shape,
+            QueryFilter::default(),
+            |handle| {
+                if let Some(collider) = self.collider_set.get(handle) {
+                    tags.push(collider.user_data);
+                }
+                true
+            },
         );
+
+        tags
+    }
+
+    /// The closest collider to `point` whose tag passes `accept`, along with the distance
+    /// to its surface. A point inside a collider is at distance 0.
+    pub fn nearest(
+        &self,
+        point: (f32, f32),
+        max_distance: f32,
+        accept: impl Fn(u128) -> bool,
+    ) -> Option<(u128, f32)> {
+        let predicate = |_: ColliderHandle, collider: &Collider| accept(collider.user_data);
+        let filter = QueryFilter::default().predicate(&predicate);
+        let point = tuple_to_point(point);
+
+        let (handle, projection) = self.query_pipeline.project_point(
+            &self.rigidbody_set,
+            &self.collider_set,
+            &point,
+            true,
+            filter,
+        )?;
+        let distance = (projection.point - point).norm();
+
+        (distance <= max_distance).then(|| (self.collider_set[handle].user_data, distance))
     }
 
     pub fn get(&mut self, physics: &mut Physics) -> Option<PhysicsController> {
@@ -134,3 +606,7 @@ impl PhysicalWorld {
 fn tuple_to_vec(tuple: (f32, f32)) -> Vector2<Real> {
     vector![tuple.0, tuple.1]
 }
+
+fn tuple_to_point(tuple: (f32, f32)) -> Point<Real> {
+    point![tuple.0, tuple.1]
+}
@@ -0,0 +1,11 @@
+/// Physics runs in SI units (meters, kilograms, newtons, seconds). Only the render path
+/// should convert world lengths into pixels.
+pub const PIXELS_PER_METER: f32 = 50.0;
+
+pub fn to_pixels(meters: f32) -> f32 {
+    meters * PIXELS_PER_METER
+}
+
+pub fn to_meters(pixels: f32) -> f32 {
+    pixels / PIXELS_PER_METER
+}
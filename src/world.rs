@@ -1,114 +1,831 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::str::FromStr;
 
-use crate::entity::TypedEntity;
-use crate::theory::physics::PhysicalWorld;
-use ggez::graphics::ScreenImage;
-use ggez::{graphics, Context, GameResult};
-use rand::{thread_rng, RngCore};
+use crate::entity::docking::DockingPort;
+use crate::entity::registry::{EntityRegistry, RegistryError};
+use crate::entity::{Capabilities, Entity};
+use crate::theory::physics::{Joint, PhysicalWorld, Physics, PhysicsSnapshot};
+use ggez::GameError;
+use serde::de::{self, DeserializeSeed, MapAccess, Visitor};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-pub type WorldKey = u32;
+/// A handle to an entity. The slot index is reused once the entity is removed, but the
+/// generation is not, so a key kept after its entity is gone never finds a new one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct WorldKey {
+    index: u32,
+    generation: u32,
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error("'{0}' is not a world key; expected <index>v<generation>, like 3v0")]
+pub struct ParseKeyError(String);
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct WorldValue {
-    pub entity: TypedEntity,
-    pub screen_image: ScreenImage,
+    pub entity: Box<dyn Entity>,
+}
+
+#[derive(Clone, Debug)]
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+/// Slots iterated in index order, so the order does not depend on hashing or on luck.
+#[derive(Clone, Debug)]
+struct Arena<T> {
+    slots: Vec<Slot<T>>,
+    free: Vec<u32>,
 }
 
 #[derive(Default, Debug)]
 pub struct World {
-    map: HashMap<WorldKey, WorldValue>,
+    map: Arena<WorldValue>,
     physical_world: PhysicalWorld,
+    registry: EntityRegistry,
+}
+
+#[derive(Clone, Debug)]
+pub struct WorldSnapshot {
+    entities: Arena<WorldValue>,
+    physics: PhysicsSnapshot,
+}
+
+/// An entity as written to disk: its kind, then its state in the same format as the rest.
+#[derive(Clone, Debug)]
+struct SavedEntity(Box<dyn Entity>);
+
+#[derive(Deserialize, PartialEq, Eq)]
+#[serde(field_identifier, rename_all = "lowercase")]
+enum SavedField {
+    Kind,
+    State,
+}
+
+/// Reads the state of an entity of `kind`
+struct SavedState<'a> {
+    registry: &'a EntityRegistry,
+    kind: &'a str,
+}
+
+/// A world that can be written to disk and loaded back exactly as it was. The generation
+/// of every slot is written along with the entities, by slot index, so saved keys stay
+/// valid. Reading one takes a registry of the kinds in it, through `SavedWorldSeed`.
+#[derive(Debug, Serialize)]
+pub struct SavedWorld {
+    generations: Vec<u32>,
+    free: Vec<u32>,
+    entities: BTreeMap<u32, SavedEntity>,
+    physics: PhysicsSnapshot,
+}
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "lowercase")]
+enum SavedWorldField {
+    Generations,
+    Free,
+    Entities,
+    Physics,
 }
 
+/// Reads a `SavedWorld`, building its entities with `.0`, usually the registry of the
+/// world it is loaded into.
+#[derive(Clone, Copy)]
+pub struct SavedWorldSeed<'a>(pub &'a EntityRegistry);
+
+/// Reads the entities of a saved world, by slot index
+#[derive(Clone, Copy)]
+struct SavedEntitiesSeed<'a>(&'a EntityRegistry);
+
+/// Reads an entity whose kind is in `.0`
+#[derive(Clone, Copy)]
+struct SavedEntitySeed<'a>(&'a EntityRegistry);
+
 pub struct EntityMapEntry<'a> {
     pub key: WorldKey,
     pub value: &'a WorldValue,
 }
 
+impl Display for WorldKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}v{}", self.index, self.generation)
+    }
+}
+
+impl FromStr for WorldKey {
+    type Err = ParseKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseKeyError(s.to_string());
+        let (index, generation) = s.split_once('v').ok_or_else(error)?;
+
+        Ok(WorldKey {
+            index: index.parse().map_err(|_| error())?,
+            generation: generation.parse().map_err(|_| error())?,
+        })
+    }
+}
+
+impl WorldKey {
+    fn to_tag(self) -> u128 {
+        (self.index as u128) << 32 | self.generation as u128
+    }
+
+    fn from_tag(tag: u128) -> Self {
+        WorldKey {
+            index: (tag >> 32) as u32,
+            generation: tag as u32,
+        }
+    }
+}
+
+impl Serialize for SavedEntity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut saved = serializer.serialize_struct("SavedEntity", 2)?;
+        saved.serialize_field("kind", self.0.kind())?;
+        saved.serialize_field("state", self.0.as_serialize())?;
+        saved.end()
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for SavedWorldSeed<'_> {
+    type Value = SavedWorld;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_struct(
+            "SavedWorld",
+            &["generations", "free", "entities", "physics"],
+            self,
+        )
+    }
+}
+
+impl<'de> Visitor<'de> for SavedWorldSeed<'_> {
+    type Value = SavedWorld;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a saved world")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let (mut generations, mut free, mut entities, mut physics) = (None, None, None, None);
+        while let Some(field) = map.next_key()? {
+            match field {
+                SavedWorldField::Generations => generations = Some(map.next_value()?),
+                SavedWorldField::Free => free = Some(map.next_value()?),
+                SavedWorldField::Entities => {
+                    entities = Some(map.next_value_seed(SavedEntitiesSeed(self.0))?)
+                }
+                SavedWorldField::Physics => physics = Some(map.next_value()?),
+            }
+        }
+
+        let generations: Vec<u32> =
+            generations.ok_or_else(|| de::Error::missing_field("generations"))?;
+        let entities: BTreeMap<u32, SavedEntity> =
+            entities.ok_or_else(|| de::Error::missing_field("entities"))?;
+        if let Some(index) = entities
+            .keys()
+            .find(|index| **index as usize >= generations.len())
+        {
+            return Err(de::Error::custom(format!(
+                "Entity in slot {index}, past the last slot"
+            )));
+        }
+
+        Ok(SavedWorld {
+            generations,
+            free: free.ok_or_else(|| de::Error::missing_field("free"))?,
+            entities,
+            physics: physics.ok_or_else(|| de::Error::missing_field("physics"))?,
+        })
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for SavedEntitiesSeed<'_> {
+    type Value = BTreeMap<u32, SavedEntity>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for SavedEntitiesSeed<'_> {
+    type Value = BTreeMap<u32, SavedEntity>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("entities by slot index")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut entities = BTreeMap::new();
+        while let Some(index) = map.next_key()? {
+            entities.insert(index, map.next_value_seed(SavedEntitySeed(self.0))?);
+        }
+
+        Ok(entities)
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for SavedEntitySeed<'_> {
+    type Value = SavedEntity;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_struct("SavedEntity", &["kind", "state"], self)
+    }
+}
+
+impl<'de> Visitor<'de> for SavedEntitySeed<'_> {
+    type Value = SavedEntity;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an entity with its kind and state")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        // The kind decides how to read the state, so it has to come first
+        if map.next_key()? != Some(SavedField::Kind) {
+            return Err(de::Error::missing_field("kind"));
+        }
+        let kind: String = map.next_value()?;
+
+        if map.next_key()? != Some(SavedField::State) {
+            return Err(de::Error::missing_field("state"));
+        }
+        let entity = map.next_value_seed(SavedState {
+            registry: self.0,
+            kind: &kind,
+        })?;
+
+        Ok(SavedEntity(entity))
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for SavedState<'_> {
+    type Value = Box<dyn Entity>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        self.registry
+            .load(self.kind, deserializer)
+            .map_err(de::Error::custom)
+    }
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+}
+
+impl<T> Arena<T> {
+    fn insert(&mut self, value: T) -> WorldKey {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    value: None,
+                });
+                (self.slots.len() - 1) as u32
+            }
+        };
+
+        let slot = &mut self.slots[index as usize];
+        slot.value = Some(value);
+
+        WorldKey {
+            index,
+            generation: slot.generation,
+        }
+    }
+
+    fn remove(&mut self, key: &WorldKey) -> Option<T> {
+        let slot = self.slots.get_mut(key.index as usize)?;
+        if slot.generation != key.generation {
+            return None;
+        }
+
+        let value = slot.value.take()?;
+        slot.generation += 1;
+        self.free.push(key.index);
+
+        Some(value)
+    }
+
+    fn get(&self, key: &WorldKey) -> Option<&T> {
+        self.slots
+            .get(key.index as usize)
+            .filter(|slot| slot.generation == key.generation)?
+            .value
+            .as_ref()
+    }
+
+    fn get_mut(&mut self, key: &WorldKey) -> Option<&mut T> {
+        self.slots
+            .get_mut(key.index as usize)
+            .filter(|slot| slot.generation == key.generation)?
+            .value
+            .as_mut()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (WorldKey, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            let key = WorldKey {
+                index: index as u32,
+                generation: slot.generation,
+            };
+            slot.value.as_ref().map(|value| (key, value))
+        })
+    }
+
+    fn iter_mut(&mut self) -> impl Iterator<Item = (WorldKey, &mut T)> {
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                let key = WorldKey {
+                    index: index as u32,
+                    generation: slot.generation,
+                };
+                slot.value.as_mut().map(|value| (key, value))
+            })
+    }
+
+    fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.slots.iter_mut().filter_map(|slot| slot.value.as_mut())
+    }
+
+    /// Goes back to `earlier`, a copy of this arena taken before. The keys of the values in
+    /// `earlier` stay valid, but keys handed out since then must not find anything again,
+    /// so every slot `earlier` leaves empty moves past the generations it has had.
+    fn restore(&mut self, earlier: Arena<T>) {
+        let Arena { mut slots, free } = earlier;
+        let generation = |index: usize| self.slots.get(index).map_or(0, |slot| slot.generation);
+
+        slots
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, slot)| slot.value.is_none())
+            .for_each(|(index, slot)| {
+                slot.generation = slot.generation.max(generation(index)) + 1;
+            });
+
+        // Slots added since then are kept, empty, to remember their generation
+        let added = slots.len()..self.slots.len();
+        added.clone().for_each(|index| {
+            slots.push(Slot {
+                generation: generation(index) + 1,
+                value: None,
+            })
+        });
+
+        self.free = added.rev().map(|index| index as u32).chain(free).collect();
+        self.slots = slots;
+    }
+}
+
 impl World {
+    pub fn iter_entity(&self) -> impl Iterator<Item = EntityMapEntry<'_>> {
+        self.map
+            .iter()
+            .map(|(key, value)| EntityMapEntry { key, value })
+    }
+
     pub fn iter_mut_entity(&mut self) -> impl Iterator<Item = &mut WorldValue> {
         self.map.values_mut()
     }
 
-    pub fn update_all_entity(&mut self, ctx: &mut Context) -> GameResult {
+    pub fn contains_key(&self, key: &WorldKey) -> bool {
+        self.map.get(key).is_some()
+    }
+
+    pub fn registry(&self) -> &EntityRegistry {
+        &self.registry
+    }
+
+    pub fn registry_mut(&mut self) -> &mut EntityRegistry {
+        &mut self.registry
+    }
+
+    pub fn keys_with(&self, capabilities: Capabilities) -> impl Iterator<Item = WorldKey> + '_ {
         self.map
-            .values_mut()
-            .try_for_each(|WorldValue { entity, .. }| {
-                let Some(physics) = entity.as_mut_rigidbody() else {
-                    return entity.inner_mut().update(ctx);
-                };
+            .iter()
+            .filter(move |(_, value)| value.entity.capabilities().contains(capabilities))
+            .map(|(key, _)| key)
+    }
+
+    /// Updates every entity and advances the physics by one tick. Stops before the physics
+    /// at the first entity that fails to update, and returns it along with its error.
+    pub fn update_all_entity(&mut self) -> Result<(), (WorldKey, GameError)> {
+        self.map
+            .iter_mut()
+            .try_for_each(|(key, WorldValue { entity, .. })| {
+                if let Some(physics) = entity.as_rigidbody() {
+                    let mut controller =
+                        self.physical_world.get(physics.get_mut_physics()).unwrap();
+
+                    // TODO: This is not good I guess..
+                    controller.reset();
+                    physics.update_physics(&mut controller);
+
+                    return Ok(());
+                }
+
+                if let Some(assembly) = entity.as_assembly() {
+                    for (part, mut physics) in assembly.get_physics() {
+                        let mut controller = self.physical_world.get(&mut physics).unwrap();
 
-                let mut controller = self.physical_world.get(physics.get_mut_physics()).unwrap();
+                        controller.reset();
+                        assembly.update_part_physics(part, &mut controller);
+                    }
 
-                // TODO: This is not good I guess..
-                controller.0.reset_forces(true);
-                controller.0.reset_torques(true);
-                physics.update_physics(&mut controller);
+                    return Ok(());
+                }
 
-                Ok(())
+                entity.update().map_err(|err| (key, err))
             })?;
 
         self.physical_world.tick();
 
+        let contacts = self.physical_world.contacts().to_vec();
+        let contacts_of = |physics: Physics| contacts.iter().filter(move |c| c.body == physics);
+
         self.map.values_mut().for_each(|WorldValue { entity, .. }| {
-            let Some(physics) = entity.as_mut_rigidbody() else {
-                    return;
-                };
+            if let Some(physics) = entity.as_rigidbody() {
+                let controller = self.physical_world.get(physics.get_mut_physics()).unwrap();
+                let (transform, motion) = (controller.to_transform(), controller.to_motion());
+
+                physics.report_transform(transform, motion);
 
-            let controller = self.physical_world.get(physics.get_mut_physics()).unwrap();
-            let transform = controller.to_transform();
+                let handle = *physics.get_mut_physics();
+                contacts_of(handle).for_each(|contact| physics.impact(contact));
+                return;
+            }
 
-            physics.report_transform(transform);
+            if let Some(assembly) = entity.as_assembly() {
+                for (part, mut physics) in assembly.get_physics() {
+                    let controller = self.physical_world.get(&mut physics).unwrap();
+                    let (transform, motion) = (controller.to_transform(), controller.to_motion());
+
+                    assembly.report_part_transform(part, transform, motion);
+                    contacts_of(physics).for_each(|contact| assembly.impact_part(part, contact));
+                }
+            }
+        });
+
+        self.map.values_mut().for_each(|WorldValue { entity, .. }| {
+            if let Some(physics) = entity.as_rigidbody() {
+                physics.sense(&self.physical_world);
+            }
         });
 
         Ok(())
     }
 
+    pub fn get(&self, key: &WorldKey) -> Option<&WorldValue> {
+        self.map.get(key)
+    }
+
     pub fn get_mut(&mut self, key: &WorldKey) -> Option<&mut WorldValue> {
         self.map.get_mut(key)
     }
 
-    pub fn insert(&mut self, ctx: &Context, mut entity: TypedEntity) -> (&WorldKey, &WorldValue) {
-        let mut rng = thread_rng();
+    pub fn insert(&mut self, entity: impl Entity + 'static) -> (WorldKey, &WorldValue) {
+        self.insert_boxed(Box::new(entity))
+    }
+
+    pub fn insert_boxed(&mut self, mut entity: Box<dyn Entity>) -> (WorldKey, &WorldValue) {
+        debug_assert!(
+            self.registry.kind_of(entity.as_ref()).is_some(),
+            "Entity kind '{}' is not registered",
+            entity.kind()
+        );
+        debug_assert_eq!(
+            entity.provided_capabilities(),
+            entity.capabilities(),
+            "Entity kind '{}' provides other capabilities than it declares",
+            entity.kind()
+        );
+
+        let key = self.map.insert(WorldValue { entity });
+        let entity = &mut self.map.get_mut(&key).unwrap().entity;
 
-        if let Some(physics_impl) = entity.as_mut_rigidbody() {
-            let physics_handle = self.physical_world.register(physics_impl.get_property());
+        if let Some(physics_impl) = entity.as_rigidbody() {
+            let physics_handle = self
+                .physical_world
+                .register(physics_impl.get_property(), key.to_tag());
             physics_impl.register_physics(physics_handle);
+        } else if let Some(assembly) = entity.as_assembly() {
+            let (parts, joints) = assembly.get_properties();
+            let physics_handles =
+                self.physical_world
+                    .register_assembly(parts, joints, key.to_tag());
+            assembly.register_physics(physics_handles);
         }
 
-        let mut key = rng.next_u32();
-        while self.map.contains_key(&key) {
-            key = rng.next_u32();
+        (key, self.map.get(&key).unwrap())
+    }
+
+    /// Entities whose bodies overlap the circle. Entities without a body, like waypoints,
+    /// are never found; neither are bodies added since the last tick.
+    pub fn query_radius(&self, center: (f32, f32), radius: f32) -> Vec<WorldKey> {
+        self.found(self.physical_world.intersect_ball(center, radius))
+    }
+
+    /// Entities whose bodies overlap the axis-aligned rectangle from `min` to `max`.
+    pub fn query_rect(&self, min: (f32, f32), max: (f32, f32)) -> Vec<WorldKey> {
+        self.found(self.physical_world.intersect_rect(min, max))
+    }
+
+    /// The entity of `kind` whose body is closest to `point`, and the distance to it.
+    pub fn nearest_of_kind(
+        &self,
+        point: (f32, f32),
+        kind: &str,
+        max_distance: f32,
+        exclude: Option<&WorldKey>,
+    ) -> Option<(WorldKey, f32)> {
+        let accept = |tag: u128| {
+            let key = WorldKey::from_tag(tag);
+
+            exclude != Some(&key)
+                && self
+                    .get(&key)
+                    .is_some_and(|value| value.entity.kind() == kind)
+        };
+
+        self.physical_world
+            .nearest(point, max_distance, accept)
+            .map(|(tag, distance)| (WorldKey::from_tag(tag), distance))
+    }
+
+    /// Locks a port of one dockable entity to a port of another with a fixed joint, which
+    /// pulls the ports onto each other, facing each other.
+    pub fn latch(
+        &mut self,
+        first: (&WorldKey, &DockingPort),
+        second: (&WorldKey, &DockingPort),
+    ) -> Option<Joint> {
+        let body = |key: &WorldKey| self.get(key)?.entity.as_dockable()?.body();
+        let bodies = (body(first.0)?, body(second.0)?);
+
+        Some(self.physical_world.latch(
+            (&bodies.0, &bodies.1),
+            (first.1.frame(false), second.1.frame(true)),
+        ))
+    }
+
+    pub fn release(&mut self, joint: &Joint) -> bool {
+        self.physical_world.release(joint)
+    }
+
+    pub fn is_latched(&self, joint: &Joint) -> bool {
+        self.physical_world.is_latched(joint)
+    }
+
+    fn found(&self, tags: Vec<u128>) -> Vec<WorldKey> {
+        let mut keys: Vec<WorldKey> = tags
+            .into_iter()
+            .map(WorldKey::from_tag)
+            .filter(|key| self.contains_key(key))
+            .collect();
+
+        // Assemblies have a collider per part
+        keys.sort();
+        keys.dedup();
+
+        keys
+    }
+
+    pub fn snapshot(&self) -> WorldSnapshot {
+        WorldSnapshot {
+            entities: self.map.clone(),
+            physics: self.physical_world.snapshot(),
         }
-        self.map.insert(
-            key,
-            WorldValue {
-                entity,
-                screen_image: graphics::ScreenImage::new(&ctx.gfx, None, 1.0, 1.0, 1),
-            },
-        );
+    }
+
+    pub fn restore(&mut self, snapshot: &WorldSnapshot) {
+        self.map.restore(snapshot.entities.clone());
 
-        self.map.get_key_value(&key).unwrap()
+        self.physical_world.restore(&snapshot.physics);
+    }
+
+    pub fn save(&self) -> SavedWorld {
+        SavedWorld {
+            generations: self.map.slots.iter().map(|slot| slot.generation).collect(),
+            free: self.map.free.clone(),
+            entities: self
+                .map
+                .iter()
+                .map(|(key, value)| (key.index, SavedEntity(value.entity.clone())))
+                .collect(),
+            physics: self.physical_world.snapshot(),
+        }
     }
-}
 
-#[macro_export]
-macro_rules! as_type {
-    (& $entity: expr, $type: ident) => {
-        #[allow(irrefutable_let_patterns)]
-        if let $crate::entity::TypedEntity::$type(inner) = &$entity {
-            Some(inner)
-        } else {
-            None
+    /// Replaces the whole world with a saved one. Every kind in it must be registered.
+    pub fn load(&mut self, saved: &SavedWorld) -> Result<(), RegistryError> {
+        let unknown = saved
+            .entities
+            .values()
+            .find(|SavedEntity(entity)| self.registry.kind_of(entity.as_ref()).is_none());
+        if let Some(SavedEntity(entity)) = unknown {
+            return Err(RegistryError::Unknown(entity.kind().to_string()));
         }
-    };
-
-    (&mut $entity: expr, $type: ident) => {
-        #[allow(irrefutable_let_patterns)]
-        if let $crate::entity::TypedEntity::$type(inner) = &mut $entity {
-            Some(inner)
-        } else {
-            None
+
+        let slots = saved
+            .generations
+            .iter()
+            .enumerate()
+            .map(|(index, generation)| Slot {
+                generation: *generation,
+                value: saved
+                    .entities
+                    .get(&(index as u32))
+                    .map(|SavedEntity(entity)| WorldValue {
+                        entity: entity.clone(),
+                    }),
+            });
+        self.map.restore(Arena {
+            slots: slots.collect(),
+            free: saved.free.clone(),
+        });
+        self.physical_world.restore(&saved.physics);
+
+        Ok(())
+    }
+
+    pub fn remove(&mut self, key: &WorldKey) -> Option<Box<dyn Entity>> {
+        let WorldValue { mut entity, .. } = self.map.remove(key)?;
+
+        if let Some(physics_impl) = entity.as_rigidbody() {
+            self.physical_world
+                .unregister(physics_impl.get_mut_physics());
+        } else if let Some(assembly) = entity.as_assembly() {
+            assembly.get_physics().iter().for_each(|(_, physics)| {
+                self.physical_world.unregister(physics);
+            });
         }
-    };
+
+        Some(entity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::planet::Planet;
+    use crate::entity::EntityKind;
+    use ggez::graphics::Color;
+
+    /// Declares a capability it does not provide
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    struct Pretender;
+
+    impl EntityKind for Pretender {
+        const NAME: &'static str = "pretender";
+        const CAPABILITIES: Capabilities = Capabilities::DRAWABLE;
+    }
+
+    impl Entity for Pretender {}
+
+    /// A kind other crates could register
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    struct Beacon {
+        frequency: f32,
+    }
+
+    impl EntityKind for Beacon {
+        const NAME: &'static str = "beacon";
+        const CAPABILITIES: Capabilities = Capabilities::empty();
+    }
+
+    impl Entity for Beacon {}
+
+    #[test]
+    fn stale_key_should_not_reach_reused_slot() {
+        let mut arena = Arena::default();
+        let first = arena.insert("first");
+        let second = arena.insert("second");
+
+        assert_eq!(arena.remove(&first), Some("first"));
+        assert_eq!(arena.remove(&first), None);
+
+        let third = arena.insert("third");
+        assert_eq!(third.index, first.index);
+        assert_eq!(arena.get(&first), None);
+        assert_eq!(arena.get(&third), Some(&"third"));
+
+        let order: Vec<_> = arena.iter().map(|(key, _)| key).collect();
+        assert_eq!(order, vec![third, second]);
+    }
+
+    #[test]
+    #[should_panic(expected = "provides other capabilities")]
+    fn entity_should_provide_the_capabilities_it_declares() {
+        let mut world = World::default();
+        world.registry_mut().register::<Pretender>().unwrap();
+
+        world.insert(Pretender);
+    }
+
+    #[test]
+    fn stale_key_should_not_survive_a_restore() {
+        let mut world = World::default();
+        let kept = world.insert(Planet::new((0.0, 0.0), 1.0, Color::WHITE)).0;
+        let snapshot = world.snapshot();
+
+        let discarded = world.insert(Planet::new((5.0, 0.0), 1.0, Color::WHITE)).0;
+        world.restore(&snapshot);
+        let inserted = world.insert(Planet::new((10.0, 0.0), 1.0, Color::WHITE)).0;
+
+        assert!(world.contains_key(&kept));
+        assert_ne!(inserted, discarded);
+        assert!(!world.contains_key(&discarded));
+        assert_eq!(world.iter_entity().count(), 2);
+    }
+
+    #[test]
+    fn registered_kind_should_survive_a_save() {
+        let mut world = World::default();
+        world.registry_mut().register::<Beacon>().unwrap();
+        let key = world.insert(Beacon { frequency: 1.5 }).0;
+
+        let source = ron::to_string(&world.save()).unwrap();
+        let saved = ron::Options::default()
+            .from_str_seed(&source, SavedWorldSeed(world.registry()))
+            .unwrap();
+
+        assert_eq!(
+            World::default().load(&saved),
+            Err(RegistryError::Unknown("beacon".to_string()))
+        );
+
+        let mut loaded = World::default();
+        *loaded.registry_mut() = world.registry().clone();
+        loaded.load(&saved).unwrap();
+        let beacon = loaded.get(&key).unwrap().entity.downcast_ref::<Beacon>();
+        assert_eq!(beacon.unwrap().frequency, 1.5);
+    }
+
+    #[test]
+    fn removed_entity_should_leave_nothing_behind() {
+        let mut world = World::default();
+        let removed = world.insert(Planet::new((0.0, 0.0), 1.0, Color::WHITE)).0;
+        let kept = world.insert(Planet::new((5.0, 0.0), 1.0, Color::WHITE)).0;
+
+        let mut entity = world.remove(&removed).unwrap();
+        let physics = *entity.as_rigidbody().unwrap().get_mut_physics();
+
+        assert!(!world.physical_world.contains(&physics));
+        assert_eq!(world.physical_world.body_count(), 1);
+        assert_eq!(world.physical_world.collider_count(), 1);
+        assert!(world.map.slots[removed.index as usize].value.is_none());
+        assert!(world.remove(&removed).is_none());
+
+        let inserted = world.insert(Planet::new((10.0, 0.0), 1.0, Color::WHITE)).0;
+        assert_eq!(inserted.index, removed.index);
+        assert!(!world.contains_key(&removed));
+        assert!(world.contains_key(&kept));
+    }
+
+    #[test]
+    fn key_should_round_trip_through_string() {
+        let key = WorldKey {
+            index: 12,
+            generation: 3,
+        };
+
+        assert_eq!(key.to_string(), "12v3");
+        assert_eq!("12v3".parse(), Ok(key));
+        assert!("12".parse::<WorldKey>().is_err());
+        assert!("av3".parse::<WorldKey>().is_err());
+    }
+
+    #[test]
+    fn world_should_find_bodies_near_a_point() {
+        let mut world = World::default();
+        let near = world.insert(Planet::new((0.0, 0.0), 1.0, Color::WHITE)).0;
+        let far = world.insert(Planet::new((10.0, 0.0), 1.0, Color::WHITE)).0;
+        world.update_all_entity().unwrap();
+
+        assert_eq!(world.query_radius((1.5, 0.0), 1.0), vec![near]);
+        assert_eq!(world.query_rect((-2.0, -2.0), (12.0, 2.0)), vec![near, far]);
+
+        let (nearest, distance) = world
+            .nearest_of_kind((7.0, 0.0), Planet::NAME, 100.0, None)
+            .unwrap();
+        assert_eq!(nearest, far);
+        assert!((distance - 2.0).abs() < 1e-3);
+
+        assert!(world
+            .nearest_of_kind((7.0, 0.0), Planet::NAME, 100.0, Some(&far))
+            .is_some_and(|(key, _)| key == near));
+    }
 }